@@ -0,0 +1,89 @@
+//! Socket activation, where hot holds the listening sockets across restarts and passes them to
+//! every run of the command as systemd does, so clients wait rather than being refused
+
+use std::{
+    io::{self, Error, ErrorKind, Result},
+    net::TcpListener,
+    os::fd::{AsRawFd, RawFd},
+};
+
+/// The first descriptor passed, after stdin, stdout and stderr
+const FIRST_FD: RawFd = 3;
+
+/// Sockets are moved into place without allocating, through an array this long
+const MAX_SOCKETS: usize = 64;
+
+/// Runs the command with `LISTEN_PID` set to its own PID, which is only known after fork
+const SET_PID: &str = "LISTEN_PID=$$ exec \"$@\"";
+
+/// The listening sockets, bound once when hot starts
+#[derive(Default)]
+pub struct Listeners(Vec<TcpListener>);
+
+impl Listeners {
+    /// Bind an address like `localhost:8080`, or a port alone on localhost
+    pub fn bind(addresses: &[String]) -> Result<Self> {
+        if addresses.len() > MAX_SOCKETS {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("at most {} sockets can be passed", MAX_SOCKETS),
+            ));
+        }
+        let mut listeners = Vec::new();
+        for address in addresses {
+            let address = match address.parse::<u16>() {
+                Ok(port) => format!("127.0.0.1:{}", port),
+                Err(_) => address.clone(),
+            };
+            let listener = TcpListener::bind(&address)
+                .map_err(|err| Error::new(err.kind(), format!("listen {}: {}", address, err)))?;
+            listeners.push(listener);
+        }
+        Ok(Self(listeners))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn fds(&self) -> Vec<RawFd> {
+        self.0.iter().map(AsRawFd::as_raw_fd).collect()
+    }
+}
+
+/// Wrap a command so it sets `LISTEN_PID` before it runs
+pub fn wrap(words: &[String]) -> Vec<String> {
+    ["/bin/sh", "-c", SET_PID, "hot"]
+        .into_iter()
+        .map(str::to_owned)
+        .chain(words.iter().cloned())
+        .collect()
+}
+
+/// Move the listening sockets to the descriptors from 3 on, left open for exec
+///
+/// This runs between fork and exec, so it may only make system calls. The sockets are
+/// duplicated out of the way first, so none is overwritten by another one moving into place.
+pub fn pass(fds: &[RawFd]) -> Result<()> {
+    let min = FIRST_FD + fds.len() as RawFd;
+    let mut moved = [0; MAX_SOCKETS];
+    for (to, &fd) in moved.iter_mut().zip(fds) {
+        *to = check(unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, min) })?;
+    }
+    for (target, &fd) in (FIRST_FD..).zip(&moved[..fds.len()]) {
+        check(unsafe { libc::dup2(fd, target) })?;
+        unsafe { libc::close(fd) };
+    }
+    Ok(())
+}
+
+fn check(result: libc::c_int) -> Result<libc::c_int> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(result)
+}
@@ -0,0 +1,180 @@
+//! A cgroup v2 of its own for every run of the command, capping its memory and CPU and holding
+//! on to every process it starts, even those leaving its process group
+
+use crate::config::{Percent, Size};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Error, ErrorKind, Result},
+    os::fd::{AsRawFd, RawFd},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The period CPU time is shared out in by `cpu.max`, in microseconds
+const CPU_PERIOD: u64 = 100_000;
+/// How long killed processes have to leave the cgroup
+const KILL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The cgroup of one run of the command, removed again once it is dropped
+pub struct Cgroup {
+    path: PathBuf,
+    /// Open before the command is started, so it can move itself in before exec
+    procs: File,
+}
+
+fn write(path: &Path, value: &str) -> Result<()> {
+    fs::write(path, value)
+        .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+/// Where the cgroup v2 hierarchy is mounted, `/sys/fs/cgroup` unless it is next to v1
+fn mount() -> Result<PathBuf> {
+    fs::read_to_string("/proc/self/mounts")?
+        .lines()
+        .map(|line| line.split(' ').collect::<Vec<_>>())
+        .find(|fields| fields.get(2) == Some(&"cgroup2"))
+        .map(|fields| PathBuf::from(fields[1]))
+        .ok_or_else(|| Error::new(ErrorKind::Unsupported, "cgroup v2 isn't mounted"))
+}
+
+/// The cgroup hot runs in, from the unified hierarchy line `0::/path` of `/proc/self/cgroup`
+fn own() -> Result<PathBuf> {
+    let mount = mount()?;
+    let path = fs::read_to_string("/proc/self/cgroup")?
+        .lines()
+        .find_map(|line| line.strip_prefix("0::").map(str::to_owned))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "hot isn't in a cgroup v2"))?;
+    Ok(match path.trim_start_matches('/') {
+        "" => mount,
+        path => mount.join(path),
+    })
+}
+
+/// Let the cgroups below `parent` use a controller
+fn enable(parent: &Path, controller: &str) -> Result<()> {
+    let available = fs::read_to_string(parent.join("cgroup.controllers"))?;
+    if !available.split_whitespace().any(|name| name == controller) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "the {} controller isn't available in {}",
+                controller,
+                parent.display()
+            ),
+        ));
+    }
+    let control = parent.join("cgroup.subtree_control");
+    if fs::read_to_string(&control)?
+        .split_whitespace()
+        .any(|name| name == controller)
+    {
+        return Ok(());
+    }
+    fs::write(&control, format!("+{}", controller)).map_err(|err| {
+        Error::new(
+            err.kind(),
+            format!(
+                "can't enable the {} controller in {}: {}, run hot in a cgroup delegated to it, \
+                 like with systemd-run --user --scope -p Delegate=yes",
+                controller,
+                parent.display(),
+                err
+            ),
+        )
+    })
+}
+
+impl Cgroup {
+    /// Create a cgroup below the one hot runs in, capping what its processes use together
+    pub fn create(memory: Option<Size>, cpu: Option<Percent>) -> Result<Self> {
+        static RUNS: AtomicU32 = AtomicU32::new(0);
+        let parent = own()?;
+        if memory.is_some() {
+            enable(&parent, "memory")?;
+        }
+        if cpu.is_some() {
+            enable(&parent, "cpu")?;
+        }
+        let name = format!(
+            "hot-{}-{}",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = parent.join(name);
+        fs::create_dir(&path)
+            .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        let procs = match OpenOptions::new()
+            .write(true)
+            .open(path.join("cgroup.procs"))
+        {
+            Ok(procs) => procs,
+            Err(err) => {
+                let _ = fs::remove_dir(&path);
+                return Err(err);
+            }
+        };
+        // from here on dropping it removes the directory again
+        let cgroup = Self { path, procs };
+        if let Some(memory) = memory {
+            write(&cgroup.path.join("memory.max"), &memory.0.to_string())?;
+        }
+        if let Some(cpu) = cpu {
+            let quota = (cpu.0 / 100.0 * CPU_PERIOD as f64).max(1000.0) as u64;
+            write(
+                &cgroup.path.join("cpu.max"),
+                &format!("{} {}", quota, CPU_PERIOD),
+            )?;
+        }
+        Ok(cgroup)
+    }
+
+    /// The file the command writes itself into
+    pub fn procs(&self) -> RawFd {
+        self.procs.as_raw_fd()
+    }
+
+    /// Whether any process is left in the cgroup
+    pub fn populated(&self) -> bool {
+        fs::read_to_string(self.path.join("cgroup.events"))
+            .is_ok_and(|events| events.lines().any(|line| line == "populated 1"))
+    }
+
+    /// Kill every process in the cgroup and wait a moment for them to leave it
+    pub fn kill(&self) -> Result<()> {
+        let kill = self.path.join("cgroup.kill");
+        if kill.exists() {
+            write(&kill, "1")?;
+        } else {
+            // kernels before 5.14 have no cgroup.kill
+            let procs = fs::read_to_string(self.path.join("cgroup.procs"))?;
+            for pid in procs.lines().filter_map(|pid| pid.parse().ok()) {
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+            }
+        }
+        let deadline = Instant::now() + KILL_TIMEOUT;
+        while self.populated() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(())
+    }
+}
+
+/// Move the process into the cgroup whose `cgroup.procs` is open as `procs`
+///
+/// This runs between fork and exec, so it may only make system calls.
+pub fn join(procs: RawFd) -> Result<()> {
+    if unsafe { libc::write(procs, b"0".as_ptr().cast(), 1) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir(&self.path) {
+            message!("hot: warning: {}: {}", self.path.display(), err);
+        }
+    }
+}
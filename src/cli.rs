@@ -0,0 +1,667 @@
+use std::fmt::Write;
+
+/// A command line option understood by hot itself
+pub struct Opt {
+    pub name: &'static str,
+    pub short: Option<char>,
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+pub const OPTS: &[Opt] = &[
+    Opt {
+        name: "help",
+        short: Some('h'),
+        value: None,
+        help: "Display this message",
+    },
+    Opt {
+        name: "config",
+        short: Some('c'),
+        value: Some("FILE"),
+        help: "Read configuration from FILE only",
+    },
+    Opt {
+        name: "env",
+        short: Some('e'),
+        value: Some("KEY=VALUE"),
+        help: "Set an environment variable for the command",
+    },
+    Opt {
+        name: "env-file",
+        short: None,
+        value: Some("FILE"),
+        help: "Read environment variables from FILE again on every reload",
+    },
+    Opt {
+        name: "profile",
+        short: Some('p'),
+        value: Some("NAME"),
+        help: "Apply the named profile from the configuration file",
+    },
+    Opt {
+        name: "cwd",
+        short: None,
+        value: Some("DIR"),
+        help: "Run the command and the build in DIR instead of the current directory",
+    },
+    Opt {
+        name: "build",
+        short: Some('b'),
+        value: Some("CMD"),
+        help: "Run CMD first on every reload and only restart when it succeeds",
+    },
+    Opt {
+        name: "pty",
+        short: None,
+        value: None,
+        help:
+            "Run the command in a pseudo-terminal, so it writes colors and lines as to a terminal",
+    },
+    Opt {
+        name: "buffer-size",
+        short: None,
+        value: Some("SIZE"),
+        help: "Read the output of the command SIZE like 64K at a time, 4K by default",
+    },
+    Opt {
+        name: "line-buffered",
+        short: None,
+        value: None,
+        help: "Write the output of the command a line at a time, so lines of stdout and stderr don't mix",
+    },
+    Opt {
+        name: "merge-output",
+        short: None,
+        value: None,
+        help: "Write the stderr of the command to stdout, like 2>&1",
+    },
+    Opt {
+        name: "prefix",
+        short: None,
+        value: None,
+        help: "Start lines of output with [out] or [err], and the PID of the command with overlap",
+    },
+    Opt {
+        name: "timestamps",
+        short: None,
+        value: None,
+        help: "Start lines of output with the time they were written at",
+    },
+    Opt {
+        name: "timestamp-format",
+        short: None,
+        value: Some("FORMAT"),
+        help: "Write timestamps in a strftime FORMAT, with %L for milliseconds, %H:%M:%S.%L by default",
+    },
+    Opt {
+        name: "binary",
+        short: None,
+        value: Some("show|suppress|hex"),
+        help: "Show output of the command which looks binary as it is, not at all or as hex, show by default",
+    },
+    Opt {
+        name: "encoding",
+        short: None,
+        value: Some("ENCODING"),
+        help: "Decode the output of the command from latin1 or windows-1252, or utf-8 replacing invalid bytes",
+    },
+    Opt {
+        name: "strip-ansi",
+        short: None,
+        value: None,
+        help: "Remove ANSI escape sequences like colors from the output of the command",
+    },
+    Opt {
+        name: "normalize-newlines",
+        short: None,
+        value: None,
+        help: "Turn \\r\\n line endings in the output of the command into \\n",
+    },
+    Opt {
+        name: "max-lines-per-sec",
+        short: None,
+        value: Some("N"),
+        help: "Show N lines of output a second at most, suppressing the rest but for the system log",
+    },
+    Opt {
+        name: "backpressure",
+        short: None,
+        value: Some("SIZE"),
+        help: "Stop reading the output of the command while more than SIZE like 4M waits for hot's output, 1M by default",
+    },
+    Opt {
+        name: "color",
+        short: None,
+        value: Some("auto|always|never"),
+        help: "Write styles always, never or when writing to a terminal, auto by default",
+    },
+    Opt {
+        name: "stdout-style",
+        short: None,
+        value: Some("STYLE"),
+        help: "Show the stdout of the command in STYLE like dim when hot writes it to a terminal",
+    },
+    Opt {
+        name: "stderr-style",
+        short: None,
+        value: Some("STYLE"),
+        help: "Show the stderr of the command in STYLE like red or bold yellow in a terminal",
+    },
+    Opt {
+        name: "interactive",
+        short: Some('i'),
+        value: None,
+        help: "Pass typed keys on to the command, with hot's own keys after the escape key",
+    },
+    Opt {
+        name: "escape-key",
+        short: None,
+        value: Some("KEY"),
+        help: "Press KEY before hot's own keys with interactive, ctrl-a by default",
+    },
+    Opt {
+        name: "stdin",
+        short: None,
+        value: Some("inherit|null|pipe"),
+        help: "Give the command the terminal, /dev/null or a pipe for interactive as stdin, pipe by default",
+    },
+    Opt {
+        name: "alt-screen",
+        short: None,
+        value: None,
+        help: "Run on the alternate screen of the terminal, leaving its scrollback as it was",
+    },
+    Opt {
+        name: "clear",
+        short: None,
+        value: None,
+        help: "Clear the screen before the command or the build starts for a reload",
+    },
+    Opt {
+        name: "title",
+        short: None,
+        value: None,
+        help: "Show the command and whether it runs, restarts or exited in the title of the terminal",
+    },
+    Opt {
+        name: "bell",
+        short: None,
+        value: Some("EVENTS"),
+        help: "Ring the bell of the terminal on-crash, on-exit or on-reload of the command, comma separated",
+    },
+    Opt {
+        name: "notify",
+        short: None,
+        value: None,
+        help: "Show a desktop notification instead of ringing the bell, where the terminal supports OSC 9",
+    },
+    Opt {
+        name: "mouse",
+        short: None,
+        value: None,
+        help: "Scroll back through the output of the command with the mouse wheel",
+    },
+    Opt {
+        name: "scrollback",
+        short: None,
+        value: Some("LINES"),
+        help: "Keep the last LINES lines of output to page back through, 10000 with --mouse",
+    },
+    Opt {
+        name: "system-log",
+        short: None,
+        value: Some("LOG"),
+        help: "Copy the output of the command to journald or syslog, with stderr as errors",
+    },
+    Opt {
+        name: "user",
+        short: None,
+        value: Some("USER"),
+        help: "Run the command and the build as USER, by name or ID, when hot runs as root",
+    },
+    Opt {
+        name: "group",
+        short: None,
+        value: Some("GROUP"),
+        help: "Run them as GROUP instead of the primary group of the user",
+    },
+    Opt {
+        name: "limit",
+        short: None,
+        value: Some("RESOURCE=VALUE"),
+        help: "Limit a resource of the command like nofile=4096 or as=2G, may be repeated",
+    },
+    Opt {
+        name: "nice",
+        short: None,
+        value: Some("N"),
+        help: "Run the command and the build with niceness N, from -20 to 19",
+    },
+    Opt {
+        name: "ionice",
+        short: None,
+        value: Some("CLASS"),
+        help: "Run them with an I/O priority like idle or best-effort:7",
+    },
+    Opt {
+        name: "cgroup-mem",
+        short: None,
+        value: Some("SIZE"),
+        help: "Run the command in a cgroup of its own, capping its memory at SIZE like 512M",
+    },
+    Opt {
+        name: "cgroup-cpu",
+        short: None,
+        value: Some("PERCENT"),
+        help: "Run the command in a cgroup of its own, capping its CPU at PERCENT like 50%",
+    },
+    Opt {
+        name: "unshare",
+        short: None,
+        value: Some("NAMESPACES"),
+        help: "Run the command in new namespaces, out of net,pid,mount",
+    },
+    Opt {
+        name: "listen",
+        short: None,
+        value: Some("ADDRESS"),
+        help: "Listen on ADDRESS like localhost:8080 and pass the socket to the command, may be repeated",
+    },
+    Opt {
+        name: "stop-signal",
+        short: None,
+        value: Some("SIGNAL"),
+        help: "Ask the command to exit with SIGNAL, SIGTERM by default",
+    },
+    Opt {
+        name: "reload-signal",
+        short: None,
+        value: Some("SIGNAL"),
+        help: "Send SIGNAL to the command to reload instead of restarting it",
+    },
+    Opt {
+        name: "kill-timeout",
+        short: None,
+        value: Some("DURATION"),
+        help: "Kill the command if it didn't exit DURATION after the stop signal, 5s by default",
+    },
+    Opt {
+        name: "restart-delay",
+        short: None,
+        value: Some("DURATION"),
+        help: "Wait DURATION between stopping the command and starting it again",
+    },
+    Opt {
+        name: "overlap",
+        short: None,
+        value: None,
+        help: "Keep the old command running until the new one is ready on restarts",
+    },
+    Opt {
+        name: "ready-delay",
+        short: None,
+        value: Some("DURATION"),
+        help: "Consider a new command ready after running for DURATION, 1s by default",
+    },
+    Opt {
+        name: "ready-tcp",
+        short: None,
+        value: Some("ADDRESS"),
+        help: "Consider the command ready once ADDRESS like localhost:8080 accepts connections",
+    },
+    Opt {
+        name: "ready-http",
+        short: None,
+        value: Some("URL"),
+        help: "Consider the command ready once an http:// URL responds successfully",
+    },
+    Opt {
+        name: "ready-regex",
+        short: None,
+        value: Some("REGEX"),
+        help: "Consider the command ready once a line of its output matches REGEX",
+    },
+    Opt {
+        name: "timeout",
+        short: None,
+        value: Some("DURATION"),
+        help: "Stop the command when a run takes longer than DURATION",
+    },
+    Opt {
+        name: "stall-timeout",
+        short: None,
+        value: Some("DURATION"),
+        help: "Restart the command when it writes no output for DURATION",
+    },
+    Opt {
+        name: "max-memory",
+        short: None,
+        value: Some("SIZE"),
+        help: "Restart the command when its processes use more than SIZE like 1G of memory",
+    },
+    Opt {
+        name: "max-cpu",
+        short: None,
+        value: Some("PERCENT"),
+        help: "Restart the command when its processes use more than PERCENT CPU for a while",
+    },
+    Opt {
+        name: "max-cpu-for",
+        short: None,
+        value: Some("DURATION"),
+        help: "How long CPU usage must stay above max-cpu for a restart, 30s by default",
+    },
+    Opt {
+        name: "every",
+        short: None,
+        value: Some("DURATION"),
+        help: "Reload every DURATION, whether files changed or not",
+    },
+    Opt {
+        name: "cron",
+        short: None,
+        value: Some("EXPRESSION"),
+        help: "Reload at the times of a cron EXPRESSION like \"0 3 * * *\"",
+    },
+    Opt {
+        name: "times",
+        short: None,
+        value: Some("N"),
+        help: "Run the command N times, then exit successfully if every run succeeded",
+    },
+    Opt {
+        name: "wait-for",
+        short: None,
+        value: Some("ADDRESS"),
+        help: "Wait for tcp://host:port to accept connections before first starting the command",
+    },
+    Opt {
+        name: "init",
+        short: None,
+        value: None,
+        help: "Run as init in a container, which hot is always ready to, passing signals on and reaping orphans",
+    },
+    Opt {
+        name: "pidfile",
+        short: None,
+        value: Some("FILE"),
+        help: "Record the PIDs in FILE, stopping a command left running by a crashed hot first",
+    },
+    Opt {
+        name: "reraise-signal",
+        short: None,
+        value: None,
+        help: "Exit by the same signal when a signal killed the command, not with 128 + signal",
+    },
+    Opt {
+        name: "restart",
+        short: None,
+        value: Some("never|always|on-failure"),
+        help: "Whether to start the command again when it exits, never by default",
+    },
+    Opt {
+        name: "max-restarts",
+        short: None,
+        value: Some("N"),
+        help: "Give up after restarting the command N times in a row since the last reload",
+    },
+    Opt {
+        name: "backoff",
+        short: None,
+        value: Some("DURATION"),
+        help: "Wait DURATION before restarting, doubled for every restart, 100ms by default",
+    },
+    Opt {
+        name: "backoff-max",
+        short: None,
+        value: Some("DURATION"),
+        help: "Wait at most DURATION between restarts, 10s by default",
+    },
+    Opt {
+        name: "crash-window",
+        short: None,
+        value: Some("DURATION"),
+        help: "Count exits within DURATION of starting as crashes, 1s by default",
+    },
+    Opt {
+        name: "crash-count",
+        short: None,
+        value: Some("N"),
+        help: "Stop restarting after N crashes in a row until reloaded, 5 by default",
+    },
+    Opt {
+        name: "watch",
+        short: Some('w'),
+        value: Some("PATH"),
+        help: "Reload when files below PATH or matching a glob change, may be repeated",
+    },
+    Opt {
+        name: "watch-list",
+        short: None,
+        value: Some("FILE"),
+        help: "Also watch the paths listed in FILE, one per line, or - for stdin",
+    },
+    Opt {
+        name: "watch-git",
+        short: None,
+        value: None,
+        help: "Reload when switching branches or committing, pulling and resetting in git",
+    },
+    Opt {
+        name: "watch-exe",
+        short: None,
+        value: None,
+        help: "Reload when the executable of the command is rebuilt",
+    },
+    Opt {
+        name: "ignore",
+        short: None,
+        value: Some("GLOB"),
+        help: "Don't reload for changes to paths matching GLOB, may be repeated",
+    },
+    Opt {
+        name: "debounce",
+        short: None,
+        value: Some("DURATION"),
+        help: "Wait until files stop changing for DURATION before reloading, 100ms by default",
+    },
+    Opt {
+        name: "poll-watch",
+        short: None,
+        value: Some("INTERVAL"),
+        help: "Look for changes every INTERVAL instead of using inotify, e.g. on NFS",
+    },
+    Opt {
+        name: "events",
+        short: None,
+        value: Some("KINDS"),
+        help: "Only reload for KINDS of changes, out of create,modify,remove,rename,metadata",
+    },
+    Opt {
+        name: "no-gitignore",
+        short: None,
+        value: None,
+        help: "Also reload for paths listed in .gitignore and .ignore files",
+    },
+    Opt {
+        name: "version",
+        short: Some('V'),
+        value: None,
+        help: "Print version information",
+    },
+];
+
+/// A builtin command of hot, taking the place of the command to run
+pub struct Subcommand {
+    pub name: &'static str,
+    pub args: &'static [&'static str],
+    pub help: &'static str,
+}
+
+pub const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "completions",
+        args: &["bash", "zsh", "fish"],
+        help: "Print a completion script for bash, zsh or fish",
+    },
+    Subcommand {
+        name: "config",
+        args: &["show"],
+        help: "Print the effective configuration and where each value came from",
+    },
+];
+
+/// Options given to hot, followed by the command to run
+#[derive(Default)]
+pub struct Matches {
+    pub opts: Vec<(&'static Opt, Option<String>)>,
+    pub subcommand: Option<&'static Subcommand>,
+    pub cmd: Vec<String>,
+}
+
+impl Matches {
+    pub fn flag(&self, name: &str) -> bool {
+        self.opts.iter().any(|(opt, _)| opt.name == name)
+    }
+}
+
+fn find_long(name: &str) -> Option<&'static Opt> {
+    OPTS.iter().find(|opt| opt.name == name)
+}
+
+fn find_short(short: char) -> Option<&'static Opt> {
+    OPTS.iter().find(|opt| opt.short == Some(short))
+}
+
+pub fn usage() -> String {
+    let mut out = String::from(
+        "USAGE\n\nhot [OPTIONS..] [--] [CMD] [ARGS..]\nhot [OPTIONS..] <SUBCOMMAND> [ARGS..]\n\nOPTIONS\n\n",
+    );
+    let flags = OPTS
+        .iter()
+        .map(|opt| {
+            let mut flag = match opt.short {
+                Some(short) => format!("-{}, --{}", short, opt.name),
+                None => format!("    --{}", opt.name),
+            };
+            if let Some(value) = opt.value {
+                let _ = write!(flag, " <{}>", value);
+            }
+            flag
+        })
+        .collect::<Vec<_>>();
+    let width = flags.iter().map(String::len).max().unwrap_or(0) + 4;
+    for (flag, opt) in flags.iter().zip(OPTS) {
+        let _ = writeln!(out, "{:width$}{}", flag, opt.help, width = width);
+    }
+    out.push_str("\nSUBCOMMANDS\n\n");
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|sub| format!("{} <{}>", sub.name, sub.args.join("|")))
+        .collect::<Vec<_>>();
+    let width = subcommands.iter().map(String::len).max().unwrap_or(0) + 4;
+    for (usage, sub) in subcommands.iter().zip(SUBCOMMANDS) {
+        let _ = writeln!(out, "{:width$}{}", usage, sub.help, width = width);
+    }
+    out.push_str(
+        r#"
+DESCRIPTION
+
+Helper to make commands reloadable. When running press 'r' to reload,
+'z' to pause, 'p' to hold back output, 'k' to stop the command until it is
+reloaded, ctrl^z to suspend, ctrl^l to clear the screen, 'q' to stop the
+command and exit like it did, 's' to send it a signal, 'e' to edit it, 'h'
+to go back to the command used before, 'i' to show how it is doing,
+pageup, pagedown, home and end to page through the output kept with
+--scrollback, '/' to search it, 'o' to write the last run's again, 'y' to
+copy the last error in it and ctrl^c or ctrl^d to quit. '?' shows these
+keys and the options in effect. Keys can be rebound in hot.toml.
+
+Settings, including the command to run, are read from hot.toml or .hotrc
+files in the current and parent directories, HOT_* environment variables
+and the options above, in increasing order of precedence. See the README
+for the configuration file format.
+
+Options for hot must come before the command. Use '--' to separate them
+when the command itself starts with a dash or is named like a subcommand."#,
+    );
+    out
+}
+
+pub fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("hot: {}\n\nTry 'hot --help' for more information.", msg);
+    std::process::exit(2);
+}
+
+fn take_value<I: Iterator<Item = String>>(
+    opt: &Opt,
+    inline: Option<String>,
+    args: &mut I,
+) -> String {
+    inline
+        .or_else(|| args.next())
+        .unwrap_or_else(|| fail(format_args!("option '--{}' requires a value", opt.name)))
+}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Matches {
+    let mut matches = Matches::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            matches.cmd = args.collect();
+            break;
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (long, None),
+            };
+            let opt = find_long(name)
+                .unwrap_or_else(|| fail(format_args!("unknown option '--{}'", name)));
+            let value = if opt.value.is_some() {
+                Some(take_value(opt, inline, &mut args))
+            } else if inline.is_some() {
+                fail(format_args!("option '--{}' doesn't take a value", opt.name))
+            } else {
+                None
+            };
+            matches.opts.push((opt, value));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            let mut chars = arg[1..].chars();
+            while let Some(short) = chars.next() {
+                let opt = find_short(short)
+                    .unwrap_or_else(|| fail(format_args!("unknown option '-{}'", short)));
+                if opt.value.is_some() {
+                    let rest = chars.as_str();
+                    let inline = (!rest.is_empty()).then(|| rest.to_owned());
+                    matches
+                        .opts
+                        .push((opt, Some(take_value(opt, inline, &mut args))));
+                    break;
+                }
+                matches.opts.push((opt, None));
+            }
+        } else {
+            matches.subcommand = SUBCOMMANDS.iter().find(|sub| sub.name == arg);
+            matches.cmd = if matches.subcommand.is_some() {
+                args.collect()
+            } else {
+                std::iter::once(arg).chain(args).collect()
+            };
+            break;
+        }
+    }
+    matches
+}
+
+pub fn parse_args() -> Matches {
+    let matches = parse(std::env::args().skip(1));
+    if matches.flag("help") {
+        println!("{}", usage());
+        std::process::exit(0);
+    } else if matches.flag("version") {
+        println!("hot {}", env!("CARGO_PKG_VERSION"));
+        std::process::exit(0);
+    }
+    matches
+}
@@ -0,0 +1,229 @@
+//! Shell completion scripts generated from the option table
+
+use crate::cli::{Opt, OPTS, SUBCOMMANDS};
+use std::fmt::Write;
+
+/// Values to offer for an option, when its value name lists them as `a|b|c`
+fn choices(opt: &Opt) -> Option<Vec<&'static str>> {
+    opt.value
+        .filter(|value| value.contains('|'))
+        .map(|value| value.split('|').collect())
+}
+
+/// Whether the value of an option names a file
+fn takes_path(opt: &Opt) -> bool {
+    opt.value
+        .is_some_and(|value| ["FILE", "PATH", "DIR"].iter().any(|v| value.contains(v)))
+}
+
+fn flags(opt: &Opt) -> Vec<String> {
+    let mut flags = vec![format!("--{}", opt.name)];
+    if let Some(short) = opt.short {
+        flags.insert(0, format!("-{}", short));
+    }
+    flags
+}
+
+fn bash() -> String {
+    let all = OPTS.iter().flat_map(flags).collect::<Vec<_>>().join(" ");
+    let with_value = OPTS
+        .iter()
+        .filter(|opt| opt.value.is_some())
+        .flat_map(flags)
+        .collect::<Vec<_>>()
+        .join("|");
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|sub| sub.name)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut values = String::new();
+    for opt in OPTS.iter().filter(|opt| opt.value.is_some()) {
+        let reply = match choices(opt) {
+            Some(choices) => format!(r#"($(compgen -W "{}" -- "$cur"))"#, choices.join(" ")),
+            None if takes_path(opt) => r#"($(compgen -f -- "$cur"))"#.into(),
+            None => "()".into(),
+        };
+        let _ = writeln!(
+            values,
+            "        {}) COMPREPLY={}; return ;;",
+            flags(opt).join("|"),
+            reply
+        );
+    }
+    let mut subs = String::new();
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            subs,
+            r#"            {}) COMPREPLY=($(compgen -W "{}" -- "$cur")); return ;;"#,
+            sub.name,
+            sub.args.join(" ")
+        );
+    }
+
+    format!(
+        r#"_hot() {{
+    local cur prev i
+    COMPREPLY=()
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            --) _command_offset $((i + 1)); return ;;
+            {with_value}) ((i++)) ;;
+            -*) ;;
+{subs}            *) _command_offset $i; return ;;
+        esac
+    done
+    case "$prev" in
+{values}    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "{all}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -c -W "{subcommands}" -- "$cur"))
+    fi
+}}
+complete -F _hot hot
+"#,
+        with_value = with_value,
+        subs = subs,
+        values = values,
+        all = all,
+        subcommands = subcommands,
+    )
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', r"'\''")
+        .replace('[', r"\[")
+        .replace(']', r"\]")
+        .replace(':', r"\:")
+}
+
+fn zsh() -> String {
+    let mut specs = String::new();
+    for opt in OPTS {
+        let flags = flags(opt);
+        let names = if flags.len() > 1 {
+            format!("{{{}}}", flags.join(","))
+        } else {
+            flags[0].clone()
+        };
+        let value = match (opt.value, choices(opt)) {
+            (_, Some(choices)) => format!(":{}:({})", opt.name, choices.join(" ")),
+            (Some(value), None) if takes_path(opt) => format!(":{}:_files", zsh_escape(value)),
+            (Some(value), None) => format!(":{}: ", zsh_escape(value)),
+            (None, None) => String::new(),
+        };
+        let _ = writeln!(
+            specs,
+            "        '*'{}'[{}]{}' \\",
+            names,
+            zsh_escape(opt.help),
+            value
+        );
+    }
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|sub| format!(r#"{}\:"{}""#, sub.name, zsh_escape(sub.help)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut subs = String::new();
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            subs,
+            "                {}) _values {} {} ;;",
+            sub.name,
+            sub.name,
+            sub.args.join(" ")
+        );
+    }
+
+    format!(
+        r#"#compdef hot
+
+_hot() {{
+    local state
+    _arguments -s -S \
+{specs}        '1: :->cmd' \
+        '*:: :->args'
+    case $state in
+        cmd)
+            _alternative \
+                'subcommands:subcommand:(({subcommands}))' \
+                'commands:command:_command_names -e'
+            ;;
+        args)
+            case $words[1] in
+{subs}                *) _normal ;;
+            esac
+            ;;
+    esac
+}}
+
+_hot "$@"
+"#,
+        specs = specs,
+        subcommands = subcommands,
+        subs = subs,
+    )
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', r"\\").replace('\'', r"\'")
+}
+
+fn fish() -> String {
+    let mut out = String::new();
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|sub| sub.name)
+        .collect::<Vec<_>>()
+        .join(" ");
+    for opt in OPTS {
+        let _ = write!(out, "complete -c hot -n '__fish_use_subcommand'");
+        if let Some(short) = opt.short {
+            let _ = write!(out, " -s {}", short);
+        }
+        let _ = write!(out, " -l {}", opt.name);
+        match (opt.value, choices(opt)) {
+            (_, Some(choices)) => {
+                let _ = write!(out, " -x -a '{}'", choices.join(" "));
+            }
+            (Some(_), None) if takes_path(opt) => out.push_str(" -r -F"),
+            (Some(_), None) => out.push_str(" -x"),
+            (None, None) => {}
+        }
+        let _ = writeln!(out, " -d '{}'", fish_escape(opt.help));
+    }
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            "complete -c hot -n '__fish_use_subcommand' -f -a '{}' -d '{}'",
+            sub.name,
+            fish_escape(sub.help)
+        );
+        let _ = writeln!(
+            out,
+            "complete -c hot -n '__fish_seen_subcommand_from {}' -f -a '{}'",
+            sub.name,
+            sub.args.join(" ")
+        );
+    }
+    let _ = writeln!(
+        out,
+        "complete -c hot -n 'not __fish_seen_subcommand_from {}' -x -a '(__fish_complete_subcommand)'",
+        subcommands
+    );
+    out
+}
+
+pub fn generate(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        _ => None,
+    }
+}
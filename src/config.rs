@@ -0,0 +1,770 @@
+use crate::{
+    cli::{self, Matches},
+    glob::Pattern,
+    keys::Action,
+    limit::Limit,
+    ready::{Dependency, Url},
+    regex::Regex,
+    theme::Theme,
+    toml::{self, Table, Value},
+};
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::{self, Write},
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Configuration file names, in order of preference within a directory
+pub const FILE_NAMES: &[&str] = &["hot.toml", ".hotrc"];
+
+/// Keys that are only valid in configuration files
+const FILE_KEYS: &[&str] = &[
+    "command", "args", "aliases", "keys", "profiles", "root", "theme",
+];
+
+/// Options that only make sense on the command line
+const CLI_ONLY: &[&str] = &["help", "version"];
+
+/// Tables whose entries are merged across layers instead of replaced
+const MERGED_KEYS: &[&str] = &["aliases", "env", "keys", "theme"];
+
+/// Name of the environment variable for a setting, e.g. `HOT_KILL_TIMEOUT`
+pub fn env_var(key: &str) -> String {
+    format!("HOT_{}", key.to_uppercase().replace('-', "_"))
+}
+
+/// Where a configuration value came from
+#[derive(Clone, Debug)]
+pub enum Source {
+    File(PathBuf),
+    Profile(PathBuf, String),
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Profile(path, name) => write!(f, "{} [profiles.{}]", path.display(), name),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+            Source::Cli => f.write_str("command line"),
+        }
+    }
+}
+
+pub struct Layer {
+    pub source: Source,
+    pub table: Table,
+}
+
+impl Layer {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        let table = toml::parse(&contents).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })?;
+        let layer = Self {
+            source: Source::File(path.to_owned()),
+            table: normalize(table),
+        };
+        layer.warn_unknown();
+        Ok(layer)
+    }
+
+    fn profiles(&self) -> Result<Option<&Table>> {
+        match self.table.get("profiles") {
+            Some(Value::Table(profiles)) => Ok(Some(profiles)),
+            Some(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: 'profiles' must be a table", self.source),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Layer for the named profile if it is defined in this file
+    pub fn profile(&self, name: &str) -> Result<Option<Self>> {
+        let path = match &self.source {
+            Source::File(path) => path,
+            _ => unreachable!("profiles are only defined in files"),
+        };
+        match self.profiles()?.and_then(|profiles| profiles.get(name)) {
+            Some(Value::Table(table)) => {
+                let layer = Self {
+                    source: Source::Profile(path.clone(), name.to_owned()),
+                    table: normalize(table.clone()),
+                };
+                layer.warn_unknown();
+                Ok(Some(layer))
+            }
+            Some(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: profile '{}' must be a table", path.display(), name),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// One layer per `HOT_*` variable that is set, so each keeps its own source
+    ///
+    /// Entries of `[keys]` are set with `HOT_<ACTION>_KEY`, like `HOT_RELOAD_KEY="r f5"`, and
+    /// of `[theme]` with `HOT_<ELEMENT>_STYLE`, where `stdout` and `stderr` are options already.
+    pub fn from_env() -> Vec<Self> {
+        let options = cli::OPTS
+            .iter()
+            .map(|opt| opt.name)
+            .filter(|name| !CLI_ONLY.contains(name))
+            .chain(["command"])
+            .filter_map(|key| {
+                let var = env_var(key);
+                let value = env::var(&var).ok()?;
+                Some(Self {
+                    source: Source::Env(var),
+                    table: Table::from([(key.to_owned(), Value::String(value))]),
+                })
+            });
+        let keys = Action::ALL.iter().filter_map(|action| {
+            let var = env_var(&format!("{}-key", action.name()));
+            let value = env::var(&var).ok()?;
+            let keys = value
+                .split_whitespace()
+                .map(|key| Value::String(key.to_owned()))
+                .collect();
+            let entry = Table::from([(action.name().to_owned(), Value::Array(keys))]);
+            Some(Self {
+                source: Source::Env(var),
+                table: Table::from([("keys".to_owned(), Value::Table(entry))]),
+            })
+        });
+        let theme = Theme::ELEMENTS
+            .iter()
+            .map(|element| format!("{}-style", element))
+            .filter(|key| !cli::OPTS.iter().any(|opt| opt.name == key))
+            .filter_map(|key| {
+                let var = env_var(&key);
+                let value = env::var(&var).ok()?;
+                let element = key.trim_end_matches("-style").to_owned();
+                let entry = Table::from([(element, Value::String(value))]);
+                Some(Self {
+                    source: Source::Env(var),
+                    table: Table::from([("theme".to_owned(), Value::Table(entry))]),
+                })
+            });
+        options.chain(keys).chain(theme).collect()
+    }
+
+    pub fn from_matches(matches: &Matches) -> Self {
+        let mut table = Table::new();
+        for (opt, value) in &matches.opts {
+            let value = match value {
+                Some(value) => Value::String(value.clone()),
+                None => Value::Boolean(true),
+            };
+            match table.remove(opt.name) {
+                Some(Value::Array(mut values)) => {
+                    values.push(value);
+                    table.insert(opt.name.to_owned(), Value::Array(values));
+                }
+                Some(prev) => {
+                    table.insert(opt.name.to_owned(), Value::Array(vec![prev, value]));
+                }
+                None => {
+                    table.insert(opt.name.to_owned(), value);
+                }
+            }
+        }
+        if matches.subcommand.is_none() && !matches.cmd.is_empty() {
+            let cmd = matches.cmd.iter().cloned().map(Value::String).collect();
+            table.insert("command".into(), Value::Array(cmd));
+            table.insert("args".into(), Value::Array(Vec::new()));
+        }
+        Self {
+            source: Source::Cli,
+            table,
+        }
+    }
+
+    fn warn_unknown(&self) {
+        for key in self.table.keys() {
+            let known =
+                FILE_KEYS.contains(&key.as_str()) || cli::OPTS.iter().any(|opt| opt.name == key);
+            if !known {
+                message!("hot: warning: unknown key '{}' in {}", key, self.source);
+            }
+        }
+    }
+}
+
+/// The file given with `--config` or `HOT_CONFIG`, which disables discovery
+fn explicit_file(cli: &Layer, env: &[Layer]) -> Result<Option<PathBuf>> {
+    let value = cli
+        .table
+        .get("config")
+        .or_else(|| env.iter().find_map(|layer| layer.table.get("config")));
+    match value {
+        Some(value) => String::from_value(value)
+            .map(|path| Some(PathBuf::from(path)))
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, format!("invalid config: {}", err))),
+        None => Ok(None),
+    }
+}
+
+/// Find configuration files from the current directory upwards, stopping at the
+/// filesystem root or at a file with `root = true`. The outermost file comes first.
+fn discover() -> Result<Vec<Layer>> {
+    let cwd = env::current_dir()?;
+    let mut files = Vec::new();
+    for dir in cwd.ancestors() {
+        let path = FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file());
+        if let Some(path) = path {
+            let layer = Layer::from_file(path.strip_prefix(&cwd).unwrap_or(&path))?;
+            let root = matches!(layer.table.get("root"), Some(Value::Boolean(true)));
+            files.push(layer);
+            if root {
+                break;
+            }
+        }
+    }
+    files.reverse();
+    Ok(files)
+}
+
+/// Replace a command named after an alias with the aliased command line
+fn expand_alias(layers: &[Layer], cli: &mut Layer) -> Result<()> {
+    let cmd = match cli.table.get_mut("command") {
+        Some(Value::Array(cmd)) => cmd,
+        _ => return Ok(()),
+    };
+    let name = match cmd.first() {
+        Some(Value::String(name)) => name.clone(),
+        _ => return Ok(()),
+    };
+    let alias = layers
+        .iter()
+        .rev()
+        .find_map(|layer| match layer.table.get("aliases") {
+            Some(Value::Table(aliases)) => aliases.get(&name).map(|alias| (alias, &layer.source)),
+            _ => None,
+        });
+    if let Some((alias, source)) = alias {
+        let invalid = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid alias '{}' in {}: {}", name, source, err),
+            )
+        };
+        let words = match alias {
+            Value::String(line) => split_words(line).map_err(invalid)?,
+            alias => Vec::<String>::from_value(alias).map_err(invalid)?,
+        };
+        if words.is_empty() {
+            return Err(invalid("empty command".into()));
+        }
+        cmd.splice(..1, words.into_iter().map(Value::String));
+    }
+    Ok(())
+}
+
+/// Accept `snake_case` keys as aliases of the `kebab-case` option names
+fn normalize(table: Table) -> Table {
+    table
+        .into_iter()
+        .map(|(key, value)| (key.replace('_', "-"), value))
+        .collect()
+}
+
+/// Configuration layers, ordered from lowest to highest precedence
+pub struct Config {
+    pub layers: Vec<Layer>,
+}
+
+impl Config {
+    pub fn load(matches: &Matches) -> Result<Self> {
+        let env = Layer::from_env();
+        let cli = Layer::from_matches(matches);
+        let from_env = |key| env.iter().find_map(|layer| layer.table.get(key));
+        let files = match explicit_file(&cli, &env)? {
+            Some(path) => vec![Layer::from_file(&path)?],
+            None => discover()?,
+        };
+        let profile = cli
+            .table
+            .get("profile")
+            .or_else(|| from_env("profile"))
+            .or_else(|| {
+                files
+                    .iter()
+                    .rev()
+                    .find_map(|file| file.table.get("profile"))
+            })
+            .map(|name| {
+                String::from_value(name).map_err(|err| {
+                    Error::new(ErrorKind::InvalidInput, format!("invalid profile: {}", err))
+                })
+            })
+            .transpose()?;
+
+        let mut layers = Vec::new();
+        let mut found_profile = false;
+        for file in files {
+            let profile = match &profile {
+                Some(name) => file.profile(name)?,
+                None => None,
+            };
+            layers.push(file);
+            if let Some(profile) = profile {
+                found_profile = true;
+                layers.push(profile);
+            }
+        }
+        if let (Some(name), false) = (&profile, found_profile) {
+            let mut available = Vec::new();
+            for layer in &layers {
+                if let Some(profiles) = layer.profiles()? {
+                    available.extend(profiles.keys().cloned());
+                }
+            }
+            available.sort();
+            available.dedup();
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "unknown profile '{}' (available: {})",
+                    name,
+                    if available.is_empty() {
+                        "none".into()
+                    } else {
+                        available.join(", ")
+                    }
+                ),
+            ));
+        }
+        let mut cli = cli;
+        expand_alias(&layers, &mut cli)?;
+        layers.extend(env);
+        layers.push(cli);
+        Ok(Self { layers })
+    }
+
+    pub fn get(&self, key: &str) -> Option<(&Value, &Source)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.table.get(key).map(|value| (value, &layer.source)))
+    }
+
+    /// The effective value of every setting, annotated with where it came from
+    pub fn show(&self) -> String {
+        let mut keys = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.table.keys())
+            .map(String::as_str)
+            .filter(|key| !matches!(*key, "profiles" | "root" | "config"))
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let mut lines = Vec::new();
+        for key in keys {
+            if MERGED_KEYS.contains(&key) {
+                let mut entries = Table::new();
+                let mut sources = BTreeMap::new();
+                for (value, source) in self.get_all(key) {
+                    let table = match value {
+                        Value::Table(table) => table.clone(),
+                        value => Vec::<String>::from_value(value)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|pair| {
+                                let (k, v) = pair.split_once('=')?;
+                                Some((k.to_owned(), Value::String(v.to_owned())))
+                            })
+                            .collect(),
+                    };
+                    for (name, value) in table {
+                        sources.insert(name.clone(), source);
+                        entries.insert(name, value);
+                    }
+                }
+                for (name, value) in entries {
+                    lines.push((format!("{}.{} = {}", key, name, value), sources[&name]));
+                }
+            } else if let Some((value, source)) = self.get(key) {
+                lines.push((format!("{} = {}", key, value), source));
+            }
+        }
+
+        let files = self
+            .layers
+            .iter()
+            .filter_map(|layer| match &layer.source {
+                Source::File(path) => Some(path.display().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut out = if files.is_empty() {
+            String::from("# no configuration files found\n")
+        } else {
+            format!("# configuration files: {}\n", files.join(", "))
+        };
+        let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0) + 2;
+        for (line, source) in lines {
+            let _ = writeln!(out, "{:width$}# {}", line, source, width = width);
+        }
+        out
+    }
+
+    /// Every value for `key`, from lowest to highest precedence
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = (&'a Value, &'a Source)> {
+        self.layers
+            .iter()
+            .filter_map(move |layer| layer.table.get(key).map(|value| (value, &layer.source)))
+    }
+}
+
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Detects when any configuration file that is or could be loaded changes
+pub struct Watcher {
+    stamps: Vec<(PathBuf, Stamp)>,
+    checked: Instant,
+}
+
+impl Watcher {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(matches: &Matches) -> Result<Self> {
+        let paths = match explicit_file(&Layer::from_matches(matches), &Layer::from_env())? {
+            Some(path) => vec![path],
+            None => env::current_dir()?
+                .ancestors()
+                .flat_map(|dir| FILE_NAMES.iter().map(move |name| dir.join(name)))
+                .collect(),
+        };
+        Ok(Self {
+            stamps: paths
+                .into_iter()
+                .map(|path| {
+                    let stamp = stamp(&path);
+                    (path, stamp)
+                })
+                .collect(),
+            checked: Instant::now(),
+        })
+    }
+
+    /// Whether a file was created, modified or removed since the last check
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < Self::INTERVAL {
+            return false;
+        }
+        self.checked = Instant::now();
+        let mut changed = false;
+        for (path, prev) in &mut self.stamps {
+            let stamp = stamp(path);
+            if stamp != *prev {
+                *prev = stamp;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Conversion of a configuration value into a typed setting
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> std::result::Result<Self, String>;
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => Ok(value.to_string()),
+            Value::Array(values) => values
+                .last()
+                .ok_or_else(|| "expected a string".to_owned())
+                .and_then(String::from_value),
+            Value::Table(_) => Err(format!("expected a string, found {}", value.type_name())),
+        }
+    }
+}
+
+impl FromValue for u32 {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Integer(n) => u32::try_from(*n).map_err(|_| format!("{} is out of range", n)),
+            Value::String(s) => s
+                .parse()
+                .map_err(|_| format!("expected a number, found '{}'", s)),
+            Value::Array(values) => values
+                .last()
+                .ok_or_else(|| "expected a number".to_owned())
+                .and_then(u32::from_value),
+            value => Err(format!("expected a number, found {}", value.type_name())),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            Value::String(s) => match s.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(true),
+                "false" | "no" | "off" | "0" | "" => Ok(false),
+                _ => Err(format!("expected a boolean, found '{}'", s)),
+            },
+            Value::Array(values) => values
+                .last()
+                .ok_or_else(|| "expected a boolean".to_owned())
+                .and_then(bool::from_value),
+            value => Err(format!("expected a boolean, found {}", value.type_name())),
+        }
+    }
+}
+
+impl FromValue for PathBuf {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        String::from_value(value).map(PathBuf::from)
+    }
+}
+
+impl FromValue for Pattern {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        Pattern::new(&String::from_value(value)?)
+    }
+}
+
+impl FromValue for Url {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        String::from_value(value)?.parse()
+    }
+}
+
+impl FromValue for Dependency {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        String::from_value(value)?.parse()
+    }
+}
+
+impl FromValue for Limit {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        String::from_value(value)?.parse()
+    }
+}
+
+impl FromValue for Regex {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        Regex::new(&String::from_value(value)?)
+    }
+}
+
+impl FromValue for Duration {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Integer(0) => Ok(Duration::ZERO),
+            Value::Integer(_) | Value::Float(_) => Err(format!(
+                "expected a duration with a unit like \"300ms\", found {}",
+                value
+            )),
+            value => parse_duration(&String::from_value(value)?),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Array(values) => values.iter().map(T::from_value).collect(),
+            value => Ok(vec![T::from_value(value)?]),
+        }
+    }
+}
+
+/// The longest duration, far longer than anything hot waits for but short enough to add to
+/// any instant
+const LONGEST_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// Parse a duration like `300ms`, `1.5s`, `2m` or `1h`
+pub fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected a number and ms, s, m or h",
+            s
+        )
+    };
+    let s = s.trim();
+    if s == "0" {
+        return Ok(Duration::ZERO);
+    }
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(invalid)?;
+    let (number, unit) = s.split_at(split);
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(invalid()),
+    };
+    let duration = Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?;
+    Ok(duration.min(LONGEST_DURATION))
+}
+
+/// A number of bytes, written like `512M` or `2G`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub u64);
+
+impl FromValue for Size {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Integer(n) => u64::try_from(*n)
+                .map(Size)
+                .map_err(|_| format!("{} is out of range", n)),
+            value => parse_size(&String::from_value(value)?).map(Size),
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match SIZE_UNITS.iter().rev().find(|(_, scale)| self.0 >= *scale) {
+            Some((unit, scale)) => write!(f, "{:.1}{}", self.0 as f64 / *scale as f64, unit),
+            None => write!(f, "{}B", self.0),
+        }
+    }
+}
+
+/// A percentage like `90%`, which may be above 100 for CPU time on several cores
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Percent(pub f64);
+
+impl FromValue for Percent {
+    fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::Integer(n) => Ok(Percent(*n as f64)),
+            Value::Float(n) => Ok(Percent(*n)),
+            value => {
+                let s = String::from_value(value)?;
+                s.trim()
+                    .trim_end_matches('%')
+                    .parse()
+                    .map(Percent)
+                    .map_err(|_| format!("invalid percentage '{}'", s))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+const SIZE_UNITS: &[(&str, u64)] = &[
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+    ("T", 1 << 40),
+];
+
+/// Parse a number of bytes like `4096`, `512K`, `1.5G` or `2GiB`
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let invalid = || format!("invalid size '{}', expected a number and K, M, G or T", s);
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+    let unit = unit.trim().to_uppercase();
+    let unit = unit.trim_end_matches("IB").trim_end_matches('B');
+    let scale = match unit {
+        "" => 1,
+        unit => SIZE_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, scale)| *scale)
+            .ok_or_else(invalid)?,
+    };
+    Ok((number * scale as f64) as u64)
+}
+
+/// Split a string into words following basic shell quoting rules
+pub fn split_words(input: &str) -> std::result::Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".into()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated quote".into()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".into()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Quote a word so that it survives `split_words`
+pub fn quote_word(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c)) {
+        word.to_owned()
+    } else {
+        format!(r#""{}""#, word.replace('\\', r"\\").replace('"', r#"\""#))
+    }
+}
@@ -0,0 +1,192 @@
+//! Cron expressions for scheduling reloads
+//!
+//! Supports the five fields `minute hour day-of-month month day-of-week` with `*`, ranges
+//! like `1-5`, steps like `*/15`, lists like `1,15`, names like `jan` or `mon` and the
+//! shortcuts `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`. Times are local.
+
+use std::{
+    fmt, mem,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Give up looking for a matching time after this many years, as with `0 0 31 2 *`
+const MAX_YEARS: i32 = 5;
+
+/// The values of one field which match, as bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    /// Whether the field starts with `*`, which matters for how day fields combine
+    any: bool,
+}
+
+impl Field {
+    fn parse(s: &str, min: u32, max: u32, names: &[&str]) -> Result<Self, String> {
+        let value = |s: &str| -> Result<u32, String> {
+            let lower = s.to_lowercase();
+            if let Some(i) = names.iter().position(|name| *name == lower) {
+                return Ok(min + i as u32);
+            }
+            match s.parse() {
+                Ok(n) if (min..=max).contains(&n) => Ok(n),
+                Ok(n) => Err(format!("{} is out of range {}-{}", n, min, max)),
+                Err(_) => Err(format!("invalid value '{}'", s)),
+            }
+        };
+        let mut bits = 0;
+        for part in s.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse() {
+                    Ok(step) if step > 0 => (range, step),
+                    _ => return Err(format!("invalid step '{}'", step)),
+                },
+                None => (part, 1),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (value(start)?, value(end)?),
+                // a step after a single value runs to the end, as in `5/15`
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            };
+            if start > end {
+                return Err(format!("range '{}' ends before it starts", range));
+            }
+            for n in (start..=end).step_by(step) {
+                bits |= 1 << n;
+            }
+        }
+        Ok(Self {
+            bits,
+            any: s.starts_with('*'),
+        })
+    }
+
+    fn contains(&self, n: i32) -> bool {
+        self.bits & (1 << n) != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    source: String,
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl Schedule {
+    /// Whether the schedule matches the day of a local time
+    fn matches_day(&self, tm: &libc::tm) -> bool {
+        let day = self.days.contains(tm.tm_mday);
+        let weekday = self.weekdays.contains(tm.tm_wday);
+        // like cron, a day matches either field when both are restricted
+        match (self.days.any, self.weekdays.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first matching minute after `time`
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs() as libc::time_t;
+        let mut tm = localtime(secs - secs % 60 + 60)?;
+        let last_year = tm.tm_year + MAX_YEARS;
+        while tm.tm_year <= last_year {
+            if !self.months.contains(tm.tm_mon + 1) {
+                tm.tm_mon += 1;
+                tm.tm_mday = 1;
+                tm.tm_hour = 0;
+                tm.tm_min = 0;
+            } else if !self.matches_day(&tm) {
+                tm.tm_mday += 1;
+                tm.tm_hour = 0;
+                tm.tm_min = 0;
+            } else if !self.hours.contains(tm.tm_hour) {
+                tm.tm_hour += 1;
+                tm.tm_min = 0;
+            } else if !self.minutes.contains(tm.tm_min) {
+                tm.tm_min += 1;
+            } else {
+                let secs = normalize(&mut tm)?;
+                return Some(UNIX_EPOCH + Duration::from_secs(secs.try_into().ok()?));
+            }
+            normalize(&mut tm)?;
+        }
+        None
+    }
+
+    /// When the next matching minute comes
+    pub fn next(&self) -> Option<Instant> {
+        let now = SystemTime::now();
+        let at = self.next_after(now)?;
+        Some(Instant::now() + at.duration_since(now).unwrap_or_default())
+    }
+}
+
+fn localtime(secs: libc::time_t) -> Option<libc::tm> {
+    let mut tm = unsafe { mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return None;
+    }
+    Some(tm)
+}
+
+/// Carry fields which overflowed into the next ones, returning the time in seconds
+fn normalize(tm: &mut libc::tm) -> Option<libc::time_t> {
+    // let the C library work out whether daylight saving time applies
+    tm.tm_isdst = -1;
+    match unsafe { libc::mktime(tm) } {
+        -1 => None,
+        secs => Some(secs),
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields in cron expression '{}', found {}",
+                s,
+                fields.len()
+            ));
+        };
+        let mut weekdays = Field::parse(weekdays, 0, 7, WEEKDAYS)?;
+        // both 0 and 7 are Sunday
+        if weekdays.contains(7) {
+            weekdays.bits |= 1;
+        }
+        Ok(Self {
+            source: s.trim().to_owned(),
+            minutes: Field::parse(minutes, 0, 59, &[])?,
+            hours: Field::parse(hours, 0, 23, &[])?,
+            days: Field::parse(days, 1, 31, &[])?,
+            months: Field::parse(months, 1, 12, MONTHS)?,
+            weekdays,
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,65 @@
+//! Reading environment variables from `.env` files
+
+use std::{
+    fs,
+    io::{Error, Result},
+    path::Path,
+};
+
+/// Parse the value after `=`, which may be quoted
+fn value(raw: &str) -> std::result::Result<String, String> {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single quote")?;
+        return Ok(rest[..end].to_owned());
+    }
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next().ok_or("unterminated double quote")? {
+                '"' => return Ok(value),
+                '\\' => match chars.next().ok_or("unterminated double quote")? {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+    }
+    // a `#` after whitespace starts a comment in unquoted values
+    let end = raw
+        .char_indices()
+        .find(|&(i, c)| c == '#' && raw[..i].ends_with(char::is_whitespace))
+        .map_or(raw.len(), |(i, _)| i);
+    Ok(raw[..end].trim_end().to_owned())
+}
+
+/// Variables of a file with lines like `KEY=value` or `export KEY="value"`
+///
+/// Lines which can't be parsed are skipped with a warning.
+pub fn load(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+    let mut vars = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let parsed = match line.split_once('=') {
+            Some((key, raw)) if !key.trim().is_empty() && !key.trim().contains(' ') => {
+                value(raw).map(|value| (key.trim().to_owned(), value))
+            }
+            _ => Err("expected KEY=VALUE".to_owned()),
+        };
+        match parsed {
+            Ok(var) => vars.push(var),
+            Err(err) => message!("hot: warning: {}:{}: {}", path.display(), number + 1, err),
+        }
+    }
+    Ok(vars)
+}
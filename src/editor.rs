@@ -0,0 +1,129 @@
+//! A line editor for what hot asks for, like the command line to run instead
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A line being edited, with the cursor somewhere in it
+pub struct Editor {
+    chars: Vec<char>,
+    /// Where the next character is inserted
+    cursor: usize,
+    /// Earlier lines, which up and down bring back, the latest last
+    history: Vec<String>,
+    /// The line of the history shown, or its length for the line being edited
+    recalled: usize,
+    /// The line being edited while one of the history is shown
+    draft: String,
+}
+
+/// What a key did to the line
+pub enum Edit {
+    Editing,
+    /// Enter was pressed, taking the line
+    Done(String),
+    /// Escape was pressed, dropping the line
+    Cancelled,
+}
+
+impl Editor {
+    /// Edit a line, with the cursor at its end
+    pub fn new(line: &str, history: Vec<String>) -> Self {
+        let mut editor = Self {
+            chars: Vec::new(),
+            cursor: 0,
+            recalled: history.len(),
+            history,
+            draft: String::new(),
+        };
+        editor.set(line);
+        editor
+    }
+
+    /// Replace the line, with the cursor at its end
+    fn set(&mut self, line: &str) {
+        self.chars = line.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    /// Show another line of the history, or the line being edited past its end
+    fn recall(&mut self, recalled: usize) {
+        if self.recalled == self.history.len() {
+            self.draft = self.chars.iter().collect();
+        }
+        self.recalled = recalled;
+        let line = match self.history.get(recalled) {
+            Some(line) => line.clone(),
+            None => self.draft.clone(),
+        };
+        self.set(&line);
+    }
+
+    /// Edit the line as a key does, much like readline
+    pub fn key(&mut self, event: &KeyEvent) -> Edit {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Enter => return Edit::Done(self.chars.iter().collect()),
+            KeyCode::Esc => return Edit::Cancelled,
+            KeyCode::Char('c') if ctrl => return Edit::Cancelled,
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.chars.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.chars.truncate(self.cursor),
+            KeyCode::Char('w') if ctrl => {
+                let mut start = self.cursor;
+                while start > 0 && self.chars[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                while start > 0 && !self.chars[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                self.chars.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Char(c) if !ctrl => self.insert(&c.to_string()),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KeyCode::Up if self.recalled > 0 => self.recall(self.recalled - 1),
+            KeyCode::Down if self.recalled < self.history.len() => self.recall(self.recalled + 1),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.chars.len(),
+            _ => {}
+        }
+        Edit::Editing
+    }
+
+    /// Insert text at the cursor, as when it is pasted, with its line breaks as spaces
+    pub fn insert(&mut self, text: &str) {
+        let text = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect::<Vec<_>>();
+        let count = text.len();
+        self.chars.splice(self.cursor..self.cursor, text);
+        self.cursor += count;
+    }
+
+    /// The line as it is now
+    pub fn line(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// The line with the cursor in reverse video
+    pub fn render(&self) -> String {
+        let before = self.chars[..self.cursor].iter().collect::<String>();
+        let (under, after) = match self.chars.get(self.cursor) {
+            Some(c) => (*c, self.chars[self.cursor + 1..].iter().collect()),
+            None => (' ', String::new()),
+        };
+        format!("{}\x1b[7m{}\x1b[0m{}", before, under, after)
+    }
+}
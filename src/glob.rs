@@ -0,0 +1,240 @@
+//! Shell-style glob patterns for matching paths
+//!
+//! Supports `*` and `?` within a path component, `**` for any number of components,
+//! character classes like `[a-z]` or `[!0-9]`, alternatives like `*.{rs,toml}` and
+//! `\` to match the next character literally.
+
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    /// `?`, any character but `/`
+    Any,
+    /// `*`, any run of characters within a component
+    Star,
+    /// `**` as a whole component, any number of components
+    Globstar,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    /// One token list per expansion of `{a,b}` alternatives
+    alternatives: Vec<Vec<Token>>,
+}
+
+/// Whether a string contains glob syntax rather than naming a path literally
+pub fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '[', '{'])
+}
+
+/// The leading components of a glob which contain no glob syntax, e.g. `src` for `src/**/*.rs`
+pub fn base(pattern: &str) -> PathBuf {
+    let base = Path::new(pattern)
+        .components()
+        .take_while(|component| !is_glob(&component.as_os_str().to_string_lossy()))
+        .collect::<PathBuf>();
+    if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    }
+}
+
+/// A path as text without leading `./` components, the form patterns are matched against
+fn normalize(path: &Path) -> String {
+    path.components()
+        .skip_while(|component| *component == Component::CurDir)
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Expand `{a,b}` alternatives into separate patterns
+fn expand(pattern: &str) -> Result<Vec<String>, String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut depth = 0;
+    let mut open = None;
+    let mut commas = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let open = open.expect("an opening brace was seen");
+                    let prefix = chars[..open].iter().collect::<String>();
+                    let suffix = chars[i + 1..].iter().collect::<String>();
+                    let mut expanded = Vec::new();
+                    let mut start = open + 1;
+                    for end in commas.iter().copied().chain([i]) {
+                        let alternative = chars[start..end].iter().collect::<String>();
+                        for rest in expand(&format!("{}{}{}", prefix, alternative, suffix))? {
+                            expanded.push(rest);
+                        }
+                        start = end + 1;
+                    }
+                    return Ok(expanded);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if depth > 0 {
+        return Err(format!("unclosed '{{' in pattern '{}'", pattern));
+    }
+    Ok(vec![pattern.to_owned()])
+}
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                tokens.push(Token::Char(*chars.get(i).unwrap_or(&'\\')));
+            }
+            '?' => tokens.push(Token::Any),
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let starts_component = i == 0 || chars[i - 1] == '/';
+                let ends_component = matches!(chars.get(i + 2), None | Some('/'));
+                i += 1;
+                if starts_component && ends_component {
+                    tokens.push(Token::Globstar);
+                    // the separator is part of the globstar so `a/**/b` matches `a/b`
+                    i += 1;
+                } else {
+                    tokens.push(Token::Star);
+                }
+            }
+            '*' => tokens.push(Token::Star),
+            '[' => {
+                let start = i;
+                i += 1;
+                let negated = matches!(chars.get(i), Some('!' | '^'));
+                if negated {
+                    i += 1;
+                }
+                let mut ranges = Vec::new();
+                // a ']' right after the opening bracket is part of the class
+                let mut first = true;
+                loop {
+                    let c = match chars.get(i) {
+                        Some(']') if !first => break,
+                        Some(&c) => c,
+                        None => {
+                            let class = chars[start..].iter().collect::<String>();
+                            return Err(format!("unclosed '[' in pattern at '{}'", class));
+                        }
+                    };
+                    first = false;
+                    match (chars.get(i + 1), chars.get(i + 2)) {
+                        (Some('-'), Some(&end)) if end != ']' => {
+                            ranges.push((c, end));
+                            i += 3;
+                        }
+                        _ => {
+                            ranges.push((c, c));
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Class { negated, ranges });
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+fn matches_at(tokens: &[Token], text: &[char]) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    match token {
+        // `dir/**` also matches `dir` itself
+        Token::Char('/') if text.is_empty() => rest == [Token::Globstar],
+        Token::Char(c) => text.first() == Some(c) && matches_at(rest, &text[1..]),
+        Token::Any => matches!(text.first(), Some(&c) if c != '/') && matches_at(rest, &text[1..]),
+        Token::Class { negated, ranges } => match text.first() {
+            Some(&c) if c != '/' => {
+                let found = ranges.iter().any(|&(start, end)| start <= c && c <= end);
+                found != *negated && matches_at(rest, &text[1..])
+            }
+            _ => false,
+        },
+        Token::Star => {
+            for i in 0..=text.len() {
+                if matches_at(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        // a trailing `**` matches everything below, files and directories alike
+        Token::Globstar if rest.is_empty() => true,
+        Token::Globstar => {
+            matches_at(rest, text)
+                || (0..text.len())
+                    .filter(|&i| text[i] == '/')
+                    .any(|i| matches_at(rest, &text[i + 1..]))
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let source = normalize(Path::new(pattern));
+        let alternatives = expand(&source)?
+            .iter()
+            .map(|alternative| tokenize(alternative))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            source,
+            alternatives,
+        })
+    }
+
+    /// Whether the pattern can only match a path with several components
+    pub fn has_separator(&self) -> bool {
+        self.source.contains('/')
+    }
+
+    /// Whether the whole path matches, ignoring leading `./`
+    pub fn matches(&self, path: &Path) -> bool {
+        let text = normalize(path).chars().collect::<Vec<_>>();
+        self.alternatives
+            .iter()
+            .any(|tokens| matches_at(tokens, &text))
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,368 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{fmt, str::FromStr};
+
+/// Something hot can be asked to do from the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Reload,
+    Quit,
+    /// Stop the command and exit like it did
+    Exit,
+    /// Stop the command without restarting it until it is reloaded
+    Stop,
+    /// Freeze the command, or let it carry on
+    Pause,
+    /// Hold back the output of the command while it carries on, or write what was held back
+    Hold,
+    /// Stop the command along with hot, like Ctrl-Z in a shell
+    Suspend,
+    /// Clear the screen of the terminal
+    Clear,
+    /// Take keys as hot's own with `interactive`, instead of passing them on to the command,
+    /// until the escape key is pressed
+    Controls,
+    /// Send the command a signal picked from a menu
+    Signal,
+    /// Edit the command line, which the next restart runs
+    Edit,
+    /// Reload with the command line used before the current one
+    History,
+    /// Show the PID of the command, how long it has been running and how it exited last
+    Info,
+    /// Write the output of the run which ended last again
+    Replay,
+    /// Copy the last panic or error in the output kept to the clipboard
+    Copy,
+    /// Scroll the output kept up a page
+    PageUp,
+    /// Scroll the output kept down a page
+    PageDown,
+    /// Go to the first line of the output kept
+    Top,
+    /// Follow the output again
+    Bottom,
+    /// Search the output kept to scroll back through
+    Search,
+    /// Show the keys and the options in effect
+    Help,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Reload,
+        Action::Quit,
+        Action::Exit,
+        Action::Stop,
+        Action::Pause,
+        Action::Hold,
+        Action::Suspend,
+        Action::Clear,
+        Action::Controls,
+        Action::Signal,
+        Action::Edit,
+        Action::History,
+        Action::Info,
+        Action::Replay,
+        Action::Copy,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Search,
+        Action::Help,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Reload => "reload",
+            Action::Quit => "quit",
+            Action::Exit => "exit",
+            Action::Stop => "stop",
+            Action::Pause => "pause",
+            Action::Hold => "hold",
+            Action::Suspend => "suspend",
+            Action::Clear => "clear",
+            Action::Controls => "controls",
+            Action::Signal => "signal",
+            Action::Edit => "edit",
+            Action::History => "history",
+            Action::Info => "info",
+            Action::Replay => "replay",
+            Action::Copy => "copy",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Search => "search",
+            Action::Help => "help",
+        }
+    }
+
+    /// What the action does, for the help
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::Reload => "reload the command",
+            Action::Quit => "stop the command and quit",
+            Action::Exit => "stop the command and exit like it did",
+            Action::Stop => "stop the command until it is reloaded",
+            Action::Pause => "pause the command, or let it carry on",
+            Action::Hold => "hold back output, or write what was held back",
+            Action::Suspend => "suspend hot along with the command",
+            Action::Clear => "clear the screen",
+            Action::Controls => "take every key as hot's until the escape key",
+            Action::Signal => "send the command a signal",
+            Action::Edit => "edit the command for the next restart",
+            Action::History => "reload with the command used before",
+            Action::Info => "show the PID, uptime, restarts and last exit",
+            Action::Replay => "write the output of the run which ended last again",
+            Action::Copy => "copy the last panic or error to the clipboard",
+            Action::PageUp => "scroll the output kept up a page",
+            Action::PageDown => "scroll the output kept down a page",
+            Action::Top => "go to the first line of the output kept",
+            Action::Bottom => "follow the output again",
+            Action::Search => "search the output, with n and N for the next match",
+            Action::Help => "show this help",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Reload => &["r"],
+            Action::Quit => &["ctrl-c", "ctrl-d"],
+            Action::Exit => &["q"],
+            Action::Stop => &["k"],
+            Action::Pause => &["z"],
+            Action::Hold => &["p"],
+            Action::Suspend => &["ctrl-z"],
+            Action::Clear => &["ctrl-l"],
+            Action::Controls => &["c"],
+            Action::Signal => &["s"],
+            Action::Edit => &["e"],
+            Action::History => &["h"],
+            Action::Info => &["i"],
+            Action::Replay => &["o"],
+            Action::Copy => &["y"],
+            Action::PageUp => &["pageup"],
+            Action::PageDown => &["pagedown"],
+            Action::Top => &["home"],
+            Action::Bottom => &["end"],
+            Action::Search => &["/"],
+            Action::Help => &["?"],
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action '{}'", s))
+    }
+}
+
+/// A key together with the modifiers that must be held, e.g. `ctrl-r`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// The key pressed before hot's own keys with `interactive`, `ctrl-a` like in screen
+    pub fn escape() -> Self {
+        Self {
+            code: KeyCode::Char('a'),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        match (self.code, event.code) {
+            // characters match regardless of case or shift, like the original 'r' / 'R' binding
+            (KeyCode::Char(expected), KeyCode::Char(found)) => {
+                expected.eq_ignore_ascii_case(&found)
+                    && self.modifiers - KeyModifiers::SHIFT == event.modifiers - KeyModifiers::SHIFT
+            }
+            (expected, found) => expected == found && self.modifiers == event.modifiers,
+        }
+    }
+}
+
+const NAMED: &[(&str, KeyCode)] = &[
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("escape", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("space", KeyCode::Char(' ')),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("insert", KeyCode::Insert),
+    ("delete", KeyCode::Delete),
+];
+
+impl FromStr for Key {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid key '{}'", s);
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // a trailing '-' is the key itself, as in "ctrl--"
+        while let Some((modifier, tail)) = rest.split_once('-').filter(|(_, tail)| !tail.is_empty())
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+            rest = tail;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = rest.to_lowercase();
+                match NAMED.iter().find(|(named, _)| *named == name) {
+                    Some((_, code)) => *code,
+                    None => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n @ 1..=24) => KeyCode::F(n),
+                        _ => return Err(invalid()),
+                    },
+                }
+            }
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match NAMED.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+/// Keys bound to each action
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .iter()
+                .map(|&action| {
+                    let keys = action
+                        .default_keys()
+                        .iter()
+                        .map(|key| key.parse().expect("default keys are valid"))
+                        .collect();
+                    (action, keys)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Replace the keys bound to `action`
+    pub fn bind(&mut self, action: Action, keys: Vec<Key>) {
+        for (bound, bound_keys) in &mut self.bindings {
+            if *bound == action {
+                *bound_keys = keys;
+                return;
+            }
+        }
+    }
+
+    /// The keys bound to each action
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, &[Key])> {
+        self.bindings
+            .iter()
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    /// The first key bound to `action`, unless none is
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, keys)| keys.first().copied())
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|key| key.matches(event)))
+            .map(|(action, _)| *action)
+    }
+}
+
+/// The bytes a terminal sends for a key, to pass it on to a command in a pseudo-terminal
+pub fn encode(event: &KeyEvent) -> Option<Vec<u8>> {
+    let bytes = match event.code {
+        KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => vec![match c {
+            'a'..='z' | 'A'..='Z' => c.to_ascii_lowercase() as u8 & 0x1f,
+            ' ' | '@' | '2' => 0,
+            '[' | '3' => 0x1b,
+            // reported for ctrl-\, ctrl-], ctrl-^ and ctrl-_
+            '4'..='7' => c as u8 - b'4' + 0x1c,
+            '8' | '?' => 0x7f,
+            _ => return None,
+        }],
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => b"\r".to_vec(),
+        KeyCode::Tab => b"\t".to_vec(),
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Backspace => b"\x7f".to_vec(),
+        KeyCode::Esc => b"\x1b".to_vec(),
+        KeyCode::Up => b"\x1b[A".to_vec(),
+        KeyCode::Down => b"\x1b[B".to_vec(),
+        KeyCode::Right => b"\x1b[C".to_vec(),
+        KeyCode::Left => b"\x1b[D".to_vec(),
+        KeyCode::Home => b"\x1b[H".to_vec(),
+        KeyCode::End => b"\x1b[F".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::F(n @ 1..=4) => vec![0x1b, b'O', b'P' + n - 1],
+        KeyCode::F(n @ 5..=12) => {
+            let codes = [15, 17, 18, 19, 20, 21, 23, 24];
+            format!("\x1b[{}~", codes[n as usize - 5]).into_bytes()
+        }
+        _ => return None,
+    };
+    if event.modifiers.contains(KeyModifiers::ALT) {
+        return Some([&[0x1b], bytes.as_slice()].concat());
+    }
+    Some(bytes)
+}
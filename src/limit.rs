@@ -0,0 +1,108 @@
+//! Resource limits of the command, like `nofile=4096` or `as=2G`
+
+use crate::config;
+use std::{
+    io::{self, Result},
+    str::FromStr,
+};
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+/// How the values of a resource are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Count,
+    Bytes,
+    Seconds,
+}
+
+const RESOURCES: &[(&str, Resource, Unit)] = &[
+    ("as", libc::RLIMIT_AS, Unit::Bytes),
+    ("core", libc::RLIMIT_CORE, Unit::Bytes),
+    ("cpu", libc::RLIMIT_CPU, Unit::Seconds),
+    ("data", libc::RLIMIT_DATA, Unit::Bytes),
+    ("fsize", libc::RLIMIT_FSIZE, Unit::Bytes),
+    ("locks", libc::RLIMIT_LOCKS, Unit::Count),
+    ("memlock", libc::RLIMIT_MEMLOCK, Unit::Bytes),
+    ("msgqueue", libc::RLIMIT_MSGQUEUE, Unit::Bytes),
+    ("nice", libc::RLIMIT_NICE, Unit::Count),
+    ("nofile", libc::RLIMIT_NOFILE, Unit::Count),
+    ("nproc", libc::RLIMIT_NPROC, Unit::Count),
+    ("rss", libc::RLIMIT_RSS, Unit::Bytes),
+    ("rtprio", libc::RLIMIT_RTPRIO, Unit::Count),
+    ("sigpending", libc::RLIMIT_SIGPENDING, Unit::Count),
+    ("stack", libc::RLIMIT_STACK, Unit::Bytes),
+];
+
+/// A limit on a resource, setting the soft and the hard limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limit {
+    resource: Resource,
+    soft: libc::rlim_t,
+    hard: libc::rlim_t,
+}
+
+impl Limit {
+    /// Set the limit for the process
+    ///
+    /// This runs between fork and exec, so it may only make system calls.
+    pub fn apply(&self) -> Result<()> {
+        let limit = libc::rlimit {
+            rlim_cur: self.soft,
+            rlim_max: self.hard,
+        };
+        if unsafe { libc::setrlimit(self.resource, &limit) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// A value of a limit in the unit of its resource, or `unlimited`
+fn value(s: &str, unit: Unit) -> std::result::Result<libc::rlim_t, String> {
+    if matches!(s, "unlimited" | "infinity") {
+        return Ok(libc::RLIM_INFINITY);
+    }
+    match unit {
+        Unit::Count => s
+            .parse()
+            .map_err(|_| format!("expected a number, found '{}'", s)),
+        Unit::Bytes => config::parse_size(s),
+        Unit::Seconds => match s.parse() {
+            Ok(seconds) => Ok(seconds),
+            Err(_) => config::parse_duration(s).map(|duration| duration.as_secs()),
+        },
+    }
+}
+
+/// Accepts `NAME=VALUE`, or `NAME=SOFT:HARD` to keep the hard limit apart
+impl FromStr for Limit {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected RESOURCE=VALUE, found '{}'", s))?;
+        let &(name, resource, unit) = RESOURCES
+            .iter()
+            .find(|(known, ..)| *known == name.trim())
+            .ok_or_else(|| format!("unknown resource '{}'", name))?;
+        let (soft, hard) = match values.split_once(':') {
+            Some((soft, hard)) => (value(soft.trim(), unit)?, value(hard.trim(), unit)?),
+            None => {
+                let value = value(values.trim(), unit)?;
+                (value, value)
+            }
+        };
+        if soft > hard {
+            return Err(format!("soft limit of {} is above its hard limit", name));
+        }
+        Ok(Limit {
+            resource,
+            soft,
+            hard,
+        })
+    }
+}
@@ -1,47 +1,84 @@
+/// Write a line of hot's own to stderr like `eprintln`, but after the output waiting for stderr,
+/// as writes to it don't block while hot runs
+macro_rules! message {
+    ($($arg:tt)*) => {
+        $crate::output::message(&format!($($arg)*))
+    };
+}
+
+mod activation;
+mod cgroup;
+mod cli;
+mod completions;
+mod config;
+mod cron;
+mod dotenv;
+mod editor;
+mod glob;
+mod keys;
+mod limit;
+mod namespace;
+mod output;
+mod pidfile;
+mod priority;
+mod proc;
+mod pty;
+mod ready;
+mod regex;
+mod screen;
+mod scrollback;
+mod settings;
+mod signal;
+mod syslog;
+mod theme;
+mod timestamp;
+mod toml;
+#[cfg(feature = "io-uring")]
+mod uring;
+mod user;
+mod watch;
+
+use activation::Listeners;
 use ansi_term::Style;
+use cgroup::Cgroup;
+use config::{Config, Size};
 use crossterm::{
-    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{
+        poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent,
+        MouseEventKind,
+    },
     terminal,
 };
+use editor::{Edit, Editor};
+use keys::Action;
 use mio::{unix::SourceFd, Events, Interest, Poll, Registry, Token};
+use namespace::Isolation;
+use pty::Pty;
+use ready::Readiness;
+use settings::{Bell, Settings};
+use signal::Signal;
 use std::{
+    fmt,
     io::{self, ErrorKind, Read, Result, Write},
-    ops,
-    os::unix::prelude::AsRawFd,
+    mem, ops,
+    os::{
+        fd::RawFd,
+        unix::{
+            prelude::AsRawFd,
+            process::{CommandExt, ExitStatusExt},
+        },
+    },
     panic,
-    process::{Child, Command, Stdio},
-    time::Duration,
+    path::{Path, PathBuf},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-
-fn usage() {
-    println!(
-        r#"USAGE
-
-hot [OPTIONS..] [CMD] [ARGS..]
-
-OPTIONS
-
---help    Display this message
-
-DESCRIPTION
-
-Helper to make commands reloadable. When running press 'r' to reload
-and ctrl^c or ctrl^d to quit."#
-    );
-}
-
-fn parse_args() -> (String, Vec<String>) {
-    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
-    if args.is_empty() {
-        usage();
-        std::process::exit(1);
-    } else if args.len() == 1 && matches!(args[0].to_lowercase().as_str(), "-h" | "--help") {
-        usage();
-        std::process::exit(0);
-    }
-    let cmd = args.remove(0);
-    (cmd, args)
-}
+use watch::Watcher;
 
 fn wrap_raw_mode<F, T>(mut func: F) -> Result<T>
 where
@@ -71,99 +108,777 @@ where
     })
 }
 
-fn read_reload_event() -> Result<bool> {
-    wrap_raw_mode(|should_disable| {
+/// A key pressed or text pasted in the terminal, if any
+fn read_input() -> Result<Option<Event>> {
+    wrap_raw_mode(|_| {
         if poll(Duration::from_secs(0))? {
             match read()? {
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('c' | 'd'),
-                    modifiers: KeyModifiers::CONTROL,
+                event @ Event::Key(KeyEvent {
                     kind: KeyEventKind::Press,
                     ..
-                }) => {
-                    if should_disable {
-                        terminal::disable_raw_mode()?;
-                    }
-                    std::process::exit(2);
-                }
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('r' | 'R'),
-                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
-                    kind: KeyEventKind::Press,
+                }) => Ok(Some(event)),
+                event @ Event::Paste(_) => Ok(Some(event)),
+                event @ Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::ScrollUp | MouseEventKind::ScrollDown,
                     ..
-                }) => Ok(true),
-                _ => Ok(false),
+                }) => Ok(Some(event)),
+                _ => Ok(None),
             }
         } else {
-            Ok(false)
+            Ok(None)
         }
     })
 }
 
-pub struct Pipe(Vec<u8>);
+/// One of the output streams of the command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stream::Stdout => write!(f, "stdout"),
+            Stream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// Buffers of pipes which were dropped, for the next ones
+static POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// Buffers for reading the output of the command, one for each stream, taken from a pool so
+/// the pipes used while stopping a command don't allocate them again
+#[derive(Default)]
+pub struct Pipe([Vec<u8>; 2]);
 
 impl Pipe {
-    fn with_capacity(capacity: usize) -> Self {
-        Self(vec![0; capacity])
+    /// How much is copied from a stream in one go before the others get their turn
+    const BURST: usize = 256 * 1024;
+    /// How many buffers the pool keeps
+    const POOLED: usize = 4;
+
+    /// The buffer for a stream, `buffer-size` long
+    fn buffer(&mut self, stream: Stream, settings: &Settings) -> &mut [u8] {
+        let buffer = match stream {
+            Stream::Stdout => &mut self.0[0],
+            Stream::Stderr => &mut self.0[1],
+        };
+        if buffer.capacity() == 0 {
+            *buffer = POOL.lock().unwrap().pop().unwrap_or_default();
+        }
+        // the size may change when the configuration is reloaded
+        buffer.resize(settings.buffer_size, 0);
+        buffer
+    }
+
+    /// Read what can be read at once from a stream of the command
+    fn read<R: Read>(
+        &mut self,
+        reader: &mut R,
+        stream: Stream,
+        settings: &Settings,
+    ) -> io::Result<&[u8]> {
+        let buffer = self.buffer(stream, settings);
+        let read = match reader.read(buffer) {
+            // a pseudo-terminal reports the command closing it as an error rather than the end
+            Err(err) if err.raw_os_error() == Some(libc::EIO) => 0,
+            read => read?,
+        };
+        Ok(&buffer[..read])
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let mut pool = POOL.lock().unwrap();
+        for buffer in &mut self.0 {
+            if buffer.capacity() > 0 && pool.len() < Self::POOLED {
+                pool.push(mem::take(buffer));
+            }
+        }
+    }
+}
+
+/// Make reads from a pipe of the command or the build return at once when it is empty
+fn set_nonblocking(fd: RawFd) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+}
+
+/// Copy output read from a stream of the command to the same stream of hot, a line at a time
+/// with `line-buffered`, and to the system log, decoded from `encoding`, without escape
+/// sequences with `strip-ansi` and with `\n` line endings with `normalize-newlines`
+///
+/// With `binary`, output which looks binary is suppressed or shown as a hex dump, saying so
+/// the first time a stream writes some.
+///
+/// With `max-lines-per-sec`, lines beyond it are only copied to the system log, and how many
+/// were left out is shown once the second is over.
+///
+/// With `timestamps`, lines start with the time they were read at. With `prefix`, they start
+/// with the stream they were written to, and with `overlap` the PID of the command too, as the
+/// old and the new command may write at once.
+fn copy_output(
+    lines: &mut output::Lines,
+    process: u32,
+    stream: Stream,
+    bytes: &[u8],
+    settings: &Settings,
+) -> io::Result<()> {
+    let mut dumped = Vec::new();
+    let bytes = if settings.binary != output::Binary::Show && output::is_binary(bytes) {
+        if lines.binary(stream) {
+            let message = format!(
+                "[BINARY] the command wrote binary output to {}, {}",
+                stream,
+                match settings.binary {
+                    output::Binary::Hex => "showing it as hex",
+                    _ => "suppressing it",
+                }
+            );
+            message!("{}", settings.theme.alert.paint(message));
+        }
+        if settings.binary == output::Binary::Suppress {
+            return Ok(());
+        }
+        lines.hexdump(stream, bytes, &mut dumped);
+        &dumped
+    } else {
+        bytes
+    };
+    let mut decoded = Vec::new();
+    let bytes = if let Some(encoding) = settings.encoding {
+        lines.decode(stream, bytes, encoding, &mut decoded);
+        &decoded
+    } else {
+        bytes
+    };
+    let mut stripped = Vec::new();
+    let bytes = if settings.strip_ansi {
+        lines.strip(stream, bytes, &mut stripped);
+        &stripped
+    } else {
+        bytes
+    };
+    let mut normalized = Vec::new();
+    let bytes = if settings.normalize_newlines {
+        lines.normalize(stream, bytes, &mut normalized);
+        &normalized
+    } else {
+        bytes
+    };
+    if let Some(log) = &settings.system_log {
+        log.output(stream, bytes);
+    }
+    let mut limited = Vec::new();
+    let bytes = if let Some(max) = settings.max_lines_per_sec {
+        write_suppressed(lines, settings)?;
+        lines.limit(stream, bytes, max, &mut limited);
+        &limited
+    } else {
+        bytes
+    };
+    let mut decorated = Vec::new();
+    let bytes = if settings.prefix || settings.timestamps.is_some() {
+        let tag = match stream {
+            Stream::Stdout => "out",
+            Stream::Stderr => "err",
+        };
+        // the lines read at once share a time
+        let mut now = None;
+        lines.decorate(stream, bytes, &mut decorated, |to| {
+            if let Some(format) = &settings.timestamps {
+                let now = now.get_or_insert_with(|| format.now());
+                to.extend_from_slice(now.as_bytes());
+                to.push(b' ');
+            }
+            if !settings.prefix {
+                return;
+            }
+            let _ = if settings.overlap {
+                write!(to, "[{} {}] ", tag, process)
+            } else {
+                write!(to, "[{}] ", tag)
+            };
+        });
+        &decorated
+    } else {
+        bytes
+    };
+    if settings.line_buffered {
+        lines.complete(stream, bytes, |bytes| write_output(stream, bytes, settings))
+    } else {
+        write_output(stream, bytes, settings)
+    }
+}
+
+/// Show how many lines of output were suppressed within a second which is over, if any
+fn write_suppressed(lines: &mut output::Lines, settings: &Settings) -> io::Result<()> {
+    let Some(suppressed) = lines.suppressed() else {
+        return Ok(());
+    };
+    let message = format!("… {} lines suppressed", suppressed);
+    message!("{}", settings.theme.alert.paint(message));
+    Ok(())
+}
+
+/// Write output of the command to the same stream of hot, or stdout with `merge-output`, in
+/// the style of the stream
+///
+/// What hot's stream doesn't take yet is kept and written once it does, rather than waiting
+/// for it.
+fn write_output(stream: Stream, bytes: &[u8], settings: &Settings) -> io::Result<()> {
+    let style = settings.output_style(stream);
+    let to = if settings.merge_output {
+        Stream::Stdout
+    } else {
+        stream
+    };
+    let mut styled = Vec::new();
+    let bytes = if style.is_plain() {
+        bytes
+    } else {
+        write_styled(&mut styled, bytes, style)?;
+        &styled
+    };
+    if scrollback::record(to, bytes) {
+        return Ok(());
+    }
+    output::write(to, bytes)
+}
+
+fn write_styled<W: Write>(writer: &mut W, bytes: &[u8], style: Style) -> io::Result<()> {
+    if bytes.is_empty() || style.is_plain() {
+        writer.write_all(bytes)
+    } else {
+        write!(writer, "{}", style.prefix())?;
+        writer.write_all(bytes)?;
+        write!(writer, "{}", style.suffix())
+    }
+}
+
+/// Whether more than `backpressure` of the output waits for hot's streams, so output is left
+/// in the pipes and the command waits for them too instead of output piling up
+fn backed_up(settings: &Settings) -> bool {
+    output::pending() > settings.backpressure
+}
+
+/// Print the banner of one of the configured commands before running it
+fn banner(settings: &Settings, words: &[String]) {
+    message!(
+        "{}",
+        settings.theme.banner.paint(settings::quote_command(words))
+    );
+}
+
+/// Show the command and its state in the title of the terminal with `title`
+fn title(settings: &Settings, state: &str) -> Result<()> {
+    if !settings.title {
+        return Ok(());
+    }
+    screen::set_title(&format!("hot: {} [{}]", settings.command_line(), state))
+}
+
+/// Ring the bell of the terminal, or show a notification with `notify`, when `bell` includes
+/// what happened
+fn bell(settings: &Settings, rings: bool, message: &str) -> Result<()> {
+    if !rings {
+        return Ok(());
+    }
+    let message = format!("hot: {} {}", settings.command_line(), message);
+    screen::bell(&message, settings.notify)
+}
+
+/// The state of a command which exited, like `exited 1` or `killed by SIGKILL`
+fn exit_state(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited {}", code),
+        (None, Some(signal)) => format!("killed by {}", Signal(signal)),
+        (None, None) => status.to_string(),
+    }
+}
+
+/// Copy the last panic or error in the output kept to the clipboard of the terminal
+fn copy_error(settings: &Settings) -> Result<()> {
+    let message = match scrollback::last_error() {
+        Some((start, lines)) => {
+            screen::copy(&lines.join("\n"))?;
+            format!(
+                "[COPIED] {} lines of the error at line {}",
+                lines.len(),
+                start + 1
+            )
+        }
+        None if settings.kept_lines().is_some() => {
+            "[NO ERROR] no panic or error in the output kept".to_owned()
+        }
+        None => "[NO SCROLLBACK] start hot with --scrollback to keep output to copy".to_owned(),
+    };
+    message!("{}", settings.theme.status.paint(message));
+    Ok(())
+}
+
+/// The keys and the options in effect, shown by the help key
+fn help(settings: &Settings) -> Vec<String> {
+    let entry = |name: &str, value: String| format!("  {:<18} {}", name, value);
+    let list = |items: Vec<String>| items.join(", ");
+    let mut lines = vec![
+        format!("hot: {}", settings.command_line()),
+        String::new(),
+        "Keys".to_owned(),
+    ];
+    for (action, keys) in settings.keymap.bindings() {
+        let hidden = action == Action::Controls && !settings.interactive;
+        if !keys.is_empty() && !hidden {
+            let keys = list(keys.iter().map(ToString::to_string).collect());
+            lines.push(entry(&keys, action.describe().to_owned()));
+        }
+    }
+    if settings.interactive {
+        let escape = settings.escape_key.to_string();
+        lines.push(entry(&escape, "before one of the keys above".to_owned()));
+    }
+    lines.extend([String::new(), "Options".to_owned()]);
+    if !settings.build.is_empty() {
+        lines.push(entry("build", settings::quote_command(&settings.build)));
+    }
+    let watched = settings.watch_paths();
+    lines.push(entry(
+        "watch",
+        match watched.is_empty() {
+            true => "nothing".to_owned(),
+            false => list(
+                watched
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            ),
+        },
+    ));
+    if !settings.ignore.is_empty() {
+        lines.push(entry(
+            "ignore",
+            list(settings.ignore.iter().map(ToString::to_string).collect()),
+        ));
+    }
+    let mut restart = settings.restart.to_string();
+    if let Some(max) = settings.max_restarts {
+        restart.push_str(&format!(", at most {} times in a row", max));
+    }
+    lines.push(entry("restart", restart));
+    if let Some(times) = settings.times {
+        lines.push(entry("times", times.to_string()));
+    }
+    if let Some(every) = settings.every {
+        lines.push(entry("every", format!("{:?}", every)));
     }
+    if let Some(schedule) = &settings.cron {
+        lines.push(entry("cron", schedule.to_string()));
+    }
+    if let Some(signal) = settings.reload_signal {
+        lines.push(entry("reload-signal", signal.to_string()));
+    }
+    lines.push(entry(
+        "stop-signal",
+        format!(
+            "{}, killed after {:?}",
+            settings.stop_signal, settings.kill_timeout
+        ),
+    ));
+    lines
+}
+
+/// How long something has been going on, like `2h 5m 12s`
+fn uptime(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{}s", seconds),
+        (0, minutes, seconds) => format!("{}m {}s", minutes, seconds),
+        (hours, minutes, seconds) => format!("{}h {}m {}s", hours, minutes, seconds),
+    }
+}
 
-    fn transfer<R: Read, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<()> {
-        let read = reader.read(&mut self.0)?;
-        writer.write_all(&self.0[..read])
+/// Prepare to run one of the configured commands
+///
+/// Env files are read again every time, so changes to them apply on the next reload. With
+/// `user`, the variables naming the user and its home directory are set for it. Resource
+/// limits and priorities are set in the child before it runs the command.
+fn command(settings: &Settings, words: &[String], changed: Option<&Path>) -> Result<Command> {
+    let Some((program, args)) = words.split_first() else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "the command is empty",
+        ));
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(cwd) = &settings.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(user) = &settings.user {
+        command.uid(user.uid);
+        if let Some(name) = &user.name {
+            command.env("USER", name).env("LOGNAME", name);
+        }
+        if let Some(home) = &user.home {
+            command.env("HOME", home);
+        }
+    }
+    let primary = settings.user.as_ref().and_then(|user| user.gid);
+    if let Some(gid) = settings.group.map(|group| group.0).or(primary) {
+        command.gid(gid);
+    }
+    if !settings.limits.is_empty() {
+        let limits = settings.limits.clone();
+        unsafe { command.pre_exec(move || limits.iter().try_for_each(limit::Limit::apply)) };
     }
+    if let Some(nice) = settings.nice {
+        unsafe { command.pre_exec(move || nice.apply()) };
+    }
+    if let Some(ionice) = settings.ionice {
+        unsafe { command.pre_exec(move || ionice.apply()) };
+    }
+    for file in &settings.env_files {
+        command.envs(dotenv::load(file)?);
+    }
+    command.envs(&settings.env);
+    match changed {
+        Some(path) => command.env(settings::CHANGED_FILE_VAR, path),
+        None => command.env_remove(settings::CHANGED_FILE_VAR),
+    };
+    Ok(command)
 }
 
-pub struct Process(Child);
+pub struct Process {
+    child: Child,
+    /// The cgroup of its own the command runs in, with `cgroup-mem` or `cgroup-cpu`
+    cgroup: Option<Cgroup>,
+    /// The ring reading its output, unless io_uring isn't available
+    #[cfg(feature = "io-uring")]
+    ring: Option<uring::Ring>,
+    /// Partial lines of its output, with `line-buffered`
+    lines: output::Lines,
+    /// The signal it was asked to exit with, and when it is killed if it didn't by then
+    stopping: Option<(Signal, Instant)>,
+}
 
 impl Process {
     const STDOUT: Token = Token(0);
     const STDERR: Token = Token(1);
+    #[cfg(feature = "io-uring")]
+    const RING: Token = Token(5);
+    /// How often a command being stopped is looked at
+    const STOP_POLL: Duration = Duration::from_millis(10);
 
-    fn spawn(cmd: &str, args: &[String]) -> Result<Self> {
-        eprintln!(
-            "{}",
-            Style::new().bold().paint(format!(
-                "{} {}",
-                cmd,
-                args.iter()
-                    .map(|arg| {
-                        if arg.contains(char::is_whitespace) {
-                            format!(r#""{}""#, arg.replace('"', r#"\""#))
-                        } else {
-                            arg.to_owned()
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            ))
-        );
-        Ok(Self(
-            Command::new(cmd)
-                .args(args)
-                .stdin(Stdio::piped())
+    /// Start the command, telling it which file changed if that is why it is started
+    ///
+    /// The command gets a process group of its own, so that signals reach the processes it
+    /// starts too. With `pty` that group is a session of its own, with a pseudo-terminal for
+    /// stdin and stdout and another one for stderr, which hot reads just like pipes.
+    ///
+    /// With `cgroup-mem` or `cgroup-cpu` it also gets a cgroup of its own, which it joins before
+    /// exec, and with `unshare` namespaces of its own. With `stdin` set to `inherit` it becomes
+    /// the foreground of the terminal hot runs in, so it can read it. The sockets held for
+    /// `listen` are passed to it from descriptor 3 on.
+    fn spawn(settings: &Settings, listeners: &Listeners, changed: Option<&Path>) -> Result<Self> {
+        let words = settings.command_for(changed);
+        banner(settings, &words);
+        let mut command = if listeners.is_empty() {
+            command(settings, &words, changed)?
+        } else {
+            let mut command = command(settings, &activation::wrap(&words), changed)?;
+            command.env("LISTEN_FDS", listeners.len().to_string());
+            let fds = listeners.fds();
+            unsafe { command.pre_exec(move || activation::pass(&fds)) };
+            command
+        };
+        let cgroup = if settings.cgroup_mem.is_some() || settings.cgroup_cpu.is_some() {
+            Some(Cgroup::create(settings.cgroup_mem, settings.cgroup_cpu)?)
+        } else {
+            None
+        };
+        if let Some(procs) = cgroup.as_ref().map(Cgroup::procs) {
+            unsafe { command.pre_exec(move || cgroup::join(procs)) };
+        }
+        let masters = if settings.pty {
+            let output = Pty::open()?;
+            let errors = Pty::open()?;
+            command
+                .stdin(Stdio::from(output.slave.try_clone()?))
+                .stdout(Stdio::from(output.slave))
+                .stderr(Stdio::from(errors.slave));
+            unsafe { command.pre_exec(pty::make_controlling) };
+            // what is written to the master side is typed into the terminal
+            let input = output.master.try_clone()?;
+            Some((input, output.master, errors.master))
+        } else {
+            command
+                .process_group(0)
+                .stdin(match settings.stdin {
+                    settings::Stdin::Inherit => Stdio::inherit(),
+                    settings::Stdin::Null => Stdio::null(),
+                    settings::Stdin::Pipe => Stdio::piped(),
+                })
                 .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?,
-        ))
+                .stderr(Stdio::piped());
+            if settings.stdin == settings::Stdin::Inherit {
+                unsafe { command.pre_exec(pty::take_foreground) };
+            }
+            None
+        };
+        // last, as with a PID namespace only a child of the process hot started runs the
+        // command, which must already be in the session or group hot signals
+        if !settings.unshare.is_empty() {
+            let isolation = Isolation::new(&settings.unshare);
+            unsafe { command.pre_exec(move || isolation.enter()) };
+        }
+        let mut child = command.spawn()?;
+        if let Some((input, output, errors)) = masters {
+            child.stdin = Some(ChildStdin::from(input));
+            child.stdout = Some(ChildStdout::from(output));
+            child.stderr = Some(ChildStderr::from(errors));
+        }
+        Ok(Self {
+            #[cfg(feature = "io-uring")]
+            ring: uring::Ring::new(
+                child.stdout.as_ref().unwrap().as_raw_fd(),
+                child.stderr.as_ref().unwrap().as_raw_fd(),
+                settings.buffer_size,
+            )
+            .ok(),
+            child,
+            cgroup,
+            lines: output::Lines::default(),
+            stopping: None,
+        })
     }
 
-    fn register(&self, registry: &Registry) -> Result<()> {
+    /// Watch the output of the command, which is read without blocking until none is left, or
+    /// start reading it with io_uring
+    fn register(&mut self, registry: &Registry) -> Result<()> {
+        #[cfg(feature = "io-uring")]
+        if let Some(ring) = &mut self.ring {
+            let fd = ring.fd();
+            registry.register(&mut SourceFd(&fd), Self::RING, Interest::READABLE)?;
+            return ring.start();
+        }
+        for fd in [self.stdout_fd(), self.stderr_fd()] {
+            set_nonblocking(fd);
+        }
         registry.register(
-            &mut SourceFd(&self.stdout.as_ref().unwrap().as_raw_fd()),
+            &mut SourceFd(&self.stdout_fd()),
             Self::STDOUT,
             Interest::READABLE,
         )?;
         registry.register(
-            &mut SourceFd(&self.stderr.as_ref().unwrap().as_raw_fd()),
+            &mut SourceFd(&self.stderr_fd()),
             Self::STDERR,
             Interest::READABLE,
         )?;
         Ok(())
     }
 
+    /// Write input for the command
+    fn send(&mut self, bytes: &[u8]) -> Result<()> {
+        let Some(stdin) = &mut self.stdin else {
+            return Ok(());
+        };
+        match stdin.write_all(bytes) {
+            // the command may have exited or stopped reading
+            Err(err) if matches!(err.kind(), ErrorKind::BrokenPipe | ErrorKind::WouldBlock) => {
+                Ok(())
+            }
+            sent => sent,
+        }
+    }
+
+    fn stdout_fd(&self) -> RawFd {
+        self.stdout.as_ref().unwrap().as_raw_fd()
+    }
+
+    fn stderr_fd(&self) -> RawFd {
+        self.stderr.as_ref().unwrap().as_raw_fd()
+    }
+
+    /// Copy output from a stream of the command, returning what was read, and nothing once
+    /// there is none left for now
+    fn transfer<'p>(
+        &mut self,
+        pipe: &'p mut Pipe,
+        stream: Stream,
+        settings: &Settings,
+    ) -> Result<&'p [u8]> {
+        let result = match stream {
+            Stream::Stdout => pipe.read(self.child.stdout.as_mut().unwrap(), stream, settings),
+            Stream::Stderr => pipe.read(self.child.stderr.as_mut().unwrap(), stream, settings),
+        };
+        let bytes = match result {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(&[]),
+            result => result?,
+        };
+        copy_output(&mut self.lines, self.child.id(), stream, bytes, settings)?;
+        Ok(bytes)
+    }
+
+    /// Write partial lines held back for longer than `after`, and how many lines were
+    /// suppressed within a second which is over
+    fn flush_lines(&mut self, settings: &Settings, after: Duration) -> Result<()> {
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            self.lines
+                .flush(stream, after, |bytes| write_output(stream, bytes, settings))?;
+        }
+        write_suppressed(&mut self.lines, settings)
+    }
+
+    /// Copy the rest of the output of the command once it exited, including a last line
+    /// without a newline, and remove its cgroup
+    fn finish(&mut self, pipe: &mut Pipe, settings: &Settings) -> Result<()> {
+        self.drain(pipe, settings)?;
+        self.flush_lines(settings, Duration::ZERO)?;
+        self.cgroup = None;
+        Ok(())
+    }
+
+    /// Move output from a stream of the command to hot's without copying it, when nothing needs
+    /// to see or change it, returning how much that was, or nothing when it has to be copied
+    fn splice(&self, stream: Stream, settings: &Settings) -> Result<Option<usize>> {
+        // a pseudo-terminal isn't a pipe
+        if settings.pty || !settings.output_untouched(stream) || scrollback::holding() {
+            return Ok(None);
+        }
+        let fd = match stream {
+            Stream::Stdout => self.stdout_fd(),
+            Stream::Stderr => self.stderr_fd(),
+        };
+        output::splice(stream, fd)
+    }
+
     fn deregister(&self, registry: &Registry) -> Result<()> {
-        registry.deregister(&mut SourceFd(&self.stdout.as_ref().unwrap().as_raw_fd()))?;
-        registry.deregister(&mut SourceFd(&self.stderr.as_ref().unwrap().as_raw_fd()))?;
+        #[cfg(feature = "io-uring")]
+        if let Some(ring) = &self.ring {
+            return registry.deregister(&mut SourceFd(&ring.fd()));
+        }
+        registry.deregister(&mut SourceFd(&self.stdout_fd()))?;
+        registry.deregister(&mut SourceFd(&self.stderr_fd()))?;
+        Ok(())
+    }
+
+    /// Whether the command or any process it started in its group, or in its cgroup, is still
+    /// running
+    ///
+    /// The command is waited for first, as reaping the rest of its group could reap it too.
+    fn running(&mut self) -> Result<bool> {
+        Ok(self.try_wait()?.is_none()
+            || signal::group_exists(self.id())
+            || self.cgroup.as_ref().is_some_and(Cgroup::populated))
+    }
+
+    /// Ask the command and the processes it started to exit with a signal, returning whether
+    /// they all did already, which `check_stop` then finds out
+    ///
+    /// Whatever the command wrote before it exited is copied once it did, even when it had
+    /// exited already.
+    fn start_stop(&mut self, settings: &Settings, signal: Signal) -> Result<bool> {
+        if !self.running()? {
+            self.finish(&mut Pipe::default(), settings)?;
+            return Ok(true);
+        }
+        if self.stopping.is_none() {
+            signal.send_group(self.id())?;
+            // a paused command only sees the stop signal once it carries on
+            Signal::CONT.send_group(self.id())?;
+            self.stopping = Some((signal, Instant::now() + settings.kill_timeout));
+        }
+        Ok(false)
+    }
+
+    /// Copy the output of the command being stopped, returning whether it and the processes it
+    /// started exited, and kill them when they didn't after `kill-timeout`
+    ///
+    /// The cgroup is removed once everything in it exited, and killed as a whole along with the
+    /// process group, which catches processes that left the group.
+    fn check_stop(&mut self, settings: &Settings, pipe: &mut Pipe) -> Result<bool> {
+        let Some((signal, deadline)) = self.stopping else {
+            return Ok(true);
+        };
+        if !backed_up(settings) {
+            self.drain(pipe, settings)?;
+        }
+        if !self.running()? {
+            self.stopping = None;
+            // it may have written more between the copy and exiting
+            self.finish(pipe, settings)?;
+            return Ok(true);
+        }
+        if Instant::now() < deadline {
+            return Ok(false);
+        }
+        let message = format!(
+            "[KILLED] the command didn't exit within {:?} of {}",
+            settings.kill_timeout, signal
+        );
+        message!("{}", settings.theme.status.paint(message));
+        Signal::KILL.send_group(self.id())?;
+        if let Some(cgroup) = self.cgroup.take() {
+            cgroup.kill()?;
+        }
+        self.wait()?;
+        self.stopping = None;
+        self.finish(pipe, settings)?;
+        Ok(true)
+    }
+
+    /// Stop the command and the processes it started with a signal, waiting for them, as hot
+    /// does before it exits
+    fn stop_with(&mut self, settings: &Settings, signal: Signal) -> Result<()> {
+        let mut pipe = Pipe::default();
+        if self.start_stop(settings, signal)? {
+            return Ok(());
+        }
+        while !self.check_stop(settings, &mut pipe)? {
+            output::flush()?;
+            thread::sleep(Self::STOP_POLL);
+        }
+        Ok(())
+    }
+
+    /// Tell the command the size of the terminal hot runs in, when it runs in a pseudo-terminal,
+    /// which the kernel passes on as SIGWINCH
+    fn resize(&self, size: libc::winsize) -> Result<()> {
+        for fd in [self.stdout_fd(), self.stderr_fd()] {
+            if unsafe { libc::isatty(fd) } == 1 {
+                pty::resize(fd, size)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the output left in the pipes, up to their end once the command and the processes it
+    /// started exited, without waiting for processes which left its group and still hold them
+    /// open
+    fn drain(&mut self, pipe: &mut Pipe, settings: &Settings) -> Result<()> {
+        #[cfg(feature = "io-uring")]
+        if let Some(ring) = &mut self.ring {
+            loop {
+                ring.submit()?;
+                let mut copied = false;
+                while let Some((stream, bytes)) = ring.next()? {
+                    copy_output(&mut self.lines, self.child.id(), stream, bytes, settings)?;
+                    copied = true;
+                }
+                if !copied {
+                    return Ok(());
+                }
+            }
+        }
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            while !self.transfer(pipe, stream, settings)?.is_empty() {}
+        }
         Ok(())
     }
 }
@@ -171,61 +886,1733 @@ impl Process {
 impl ops::Deref for Process {
     type Target = Child;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.child
     }
 }
 
 impl ops::DerefMut for Process {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.child
     }
 }
 
-fn main() -> Result<()> {
-    let (cmd, args) = parse_args();
-    let mut poll = Poll::new()?;
-    let mut events = Events::with_capacity(128);
-
-    let mut stdout = io::stdout();
-    let mut stderr = io::stderr();
-    let mut pipe = Pipe::with_capacity(4096);
+fn main() {
+    if let Err(err) = run() {
+        // where the error stays visible
+        let _ = screen::release();
+        message!("hot: {}", err);
+        std::process::exit(1);
+    }
+}
 
-    let mut process = Process::spawn(&cmd, &args)?;
-    process.register(poll.registry())?;
-    loop {
-        if read_reload_event()? {
-            eprintln!("{}", Style::new().bold().paint("[RELOAD]"));
-            process.deregister(poll.registry())?;
-            process.kill()?;
-            let _ = process.wait()?;
-            process = Process::spawn(&cmd, &args)?;
-            process.register(poll.registry())?;
+fn subcommand(sub: &cli::Subcommand, matches: &cli::Matches) -> Result<()> {
+    let arg = match matches.cmd.as_slice() {
+        [arg] if sub.args.contains(&arg.as_str()) => arg,
+        _ => cli::fail(format_args!(
+            "'{}' expects one of: {}",
+            sub.name,
+            sub.args.join(", ")
+        )),
+    };
+    match sub.name {
+        "completions" => print!("{}", completions::generate(arg).unwrap()),
+        "config" => {
+            let config = Config::load(matches)?;
+            let settings = Settings::resolve(&config)?;
+            println!(
+                "# effective command: {}",
+                if settings.command.is_empty() {
+                    "(none)".into()
+                } else {
+                    settings.command_line()
+                }
+            );
+            print!("{}", config.show());
         }
+        _ => unreachable!("unknown subcommand"),
+    }
+    Ok(())
+}
 
-        if let Err(err) = poll.poll(&mut events, Some(Duration::from_millis(100))) {
-            if err.kind() != ErrorKind::Interrupted {
-                return Err(err);
-            }
+const WATCH: Token = Token(2);
+/// How many lines a step of the mouse wheel scrolls
+const SCROLL_LINES: isize = 3;
+
+/// A build started by a reload, which restarts the command when it succeeds
+struct Build {
+    child: Child,
+    /// The changed file that caused the reload
+    changed: Option<PathBuf>,
+    /// Partial lines of its output, with `line-buffered`
+    lines: output::Lines,
+}
+
+impl Build {
+    const STDOUT: Token = Token(6);
+    const STDERR: Token = Token(7);
+
+    /// Start the build, with its output read like the command's, so it is kept to scroll back
+    /// through and replay too
+    fn spawn(settings: &Settings, registry: &Registry, changed: Option<&Path>) -> Result<Self> {
+        let words = settings.build_for(changed);
+        banner(settings, &words);
+        // a process group of its own, so cancelling it stops what it started too
+        let child = command(settings, &words, changed)?
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.as_ref().unwrap().as_raw_fd();
+        let stderr = child.stderr.as_ref().unwrap().as_raw_fd();
+        for (fd, token) in [(stdout, Self::STDOUT), (stderr, Self::STDERR)] {
+            set_nonblocking(fd);
+            registry.register(&mut SourceFd(&fd), token, Interest::READABLE)?;
         }
-        for event in events.iter() {
-            match event.token() {
-                Process::STDERR => {
-                    if event.is_readable() {
-                        pipe.transfer(process.stderr.as_mut().unwrap(), &mut stderr)?;
+        Ok(Self {
+            child,
+            changed: changed.map(Path::to_owned),
+            lines: output::Lines::default(),
+        })
+    }
+
+    /// Copy output from the build until none is left, or until `Pipe::BURST` was copied,
+    /// returning whether there may be more
+    fn forward(&mut self, settings: &Settings, pipe: &mut Pipe) -> Result<bool> {
+        let mut copied = 0;
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            loop {
+                let result = match stream {
+                    Stream::Stdout => {
+                        pipe.read(self.child.stdout.as_mut().unwrap(), stream, settings)
                     }
-                }
-                Process::STDOUT => {
-                    if event.is_readable() {
-                        pipe.transfer(process.stdout.as_mut().unwrap(), &mut stdout)?;
+                    Stream::Stderr => {
+                        pipe.read(self.child.stderr.as_mut().unwrap(), stream, settings)
                     }
+                };
+                let bytes = match result {
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    result => result?,
+                };
+                if bytes.is_empty() {
+                    break;
+                }
+                copy_output(&mut self.lines, self.child.id(), stream, bytes, settings)?;
+                copied += bytes.len();
+                if copied >= Pipe::BURST {
+                    return Ok(true);
                 }
-                _ => {}
             }
         }
-        events.clear();
+        Ok(false)
+    }
+
+    /// Write partial lines held back for longer than `after`, and how many lines were
+    /// suppressed within a second which is over
+    fn flush_lines(&mut self, settings: &Settings, after: Duration) -> Result<()> {
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            self.lines
+                .flush(stream, after, |bytes| write_output(stream, bytes, settings))?;
+        }
+        write_suppressed(&mut self.lines, settings)
+    }
+
+    /// Copy the rest of the output of the build once it exited, including a last line without
+    /// a newline, without waiting for processes it left running which still hold the pipes
+    ///
+    /// The pipes leave the poll as they are closed along with the build.
+    fn finish(mut self, settings: &Settings) -> Result<()> {
+        let mut pipe = Pipe::default();
+        while self.forward(settings, &mut pipe)? {}
+        self.flush_lines(settings, Duration::ZERO)
+    }
+
+    /// Kill the build and everything it started
+    fn cancel(mut self, settings: &Settings) -> Result<()> {
+        Signal::KILL.send_group(self.child.id())?;
+        self.child.wait()?;
+        self.finish(settings)
+    }
+}
+
+/// The running command and the build that will replace it
+#[derive(Default)]
+struct Runner {
+    /// The sockets passed to every run of the command, with `listen`
+    listeners: Listeners,
+    process: Option<Process>,
+    /// The command replaced by an overlapped restart, until the new one is ready
+    previous: Option<Process>,
+    /// Commands asked to exit, whose output is copied until they did
+    stopping: Vec<Process>,
+    /// When the replaced command started
+    previous_started: Option<Instant>,
+    build: Option<Build>,
+    /// Restarts after the command exited since the last reload
+    restarts: u32,
+    /// When the command is started again after it exited, or once `restart-delay` passed
+    restart_at: Option<Instant>,
+    /// The changed file the command is started again for
+    restart_for: Option<PathBuf>,
+    /// When the sockets and files of the command stopped last are taken to be released
+    released_at: Option<Instant>,
+    /// The delay before the last restart, doubled for the next one
+    backoff: Option<Duration>,
+    started: Option<Instant>,
+    /// How often the command was started in all
+    launches: u32,
+    /// How the command exited by itself last
+    last_exit: Option<ExitStatus>,
+    /// Exits in a row shortly after starting
+    quick_exits: u32,
+    /// Runs which ended since the last reload, with `times`
+    runs: u32,
+    failures: u32,
+    /// The status of the last run which failed
+    failure: Option<ExitStatus>,
+    /// Whether the command was frozen with SIGSTOP
+    paused: bool,
+    /// The probes the command has yet to pass
+    readiness: Option<Readiness>,
+    /// Whether the command passed its probes, or ran for `ready-delay` without any
+    ready: bool,
+    /// When the command last wrote any output
+    output_at: Option<Instant>,
+    /// When the resources used by the command were last looked at
+    sampled_at: Option<Instant>,
+    /// CPU time the command had used then
+    cpu_ticks: Option<u64>,
+    /// Since when the command uses more CPU than `max-cpu`
+    busy_since: Option<Instant>,
+    /// Whether the escape key was pressed, so the next key is for hot, with `interactive`
+    escaped: bool,
+    /// Whether every key is for hot until the escape key is pressed, with `interactive`
+    controls: bool,
+    /// Whether the command was stopped to stay stopped until it is reloaded
+    stopped: bool,
+    /// Whether the signal menu is shown, so the next key picks the signal to send
+    picking_signal: bool,
+    /// The command line being edited, which takes the keys until it is done
+    editing: Option<Editor>,
+    /// The query of a search being typed, which takes the keys until it is done
+    query: Option<Editor>,
+    /// The command lines used so far once one was edited, the latest last
+    history: Vec<Vec<String>>,
+    /// Which of `history` is in use
+    history_at: usize,
+    /// Whether the screen is cleared before the command starts next, as it starts for a reload
+    /// with `clear`
+    clear: bool,
+    /// The line typed so far, sent to a command without a pseudo-terminal on enter
+    line: String,
+}
+
+impl Runner {
+    /// How often the resources used by the command are looked at
+    const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Restart the command, building it first when there is a build command, with a clear
+    /// screen with `clear`
+    fn reload(
+        &mut self,
+        settings: &Settings,
+        registry: &Registry,
+        changed: Option<&Path>,
+    ) -> Result<()> {
+        self.stopped = false;
+        self.restarts = 0;
+        self.backoff = None;
+        self.restart_at = None;
+        self.quick_exits = 0;
+        self.runs = 0;
+        self.failures = 0;
+        self.failure = None;
+        let changed = changed.map(|path| settings.changed_path(path));
+        let changed = changed.as_deref();
+        if let Some(build) = self.build.take() {
+            // the build is out of date already
+            build.cancel(settings)?;
+        }
+        // a build of just `{file}` has nothing to build without a changed file
+        if settings.build_for(changed).is_empty() {
+            let reloaded = self.started.is_some();
+            self.clear = settings.clear;
+            self.restart(settings, registry, changed)?;
+            let rings = reloaded && settings.bell.contains(&Bell::Reload);
+            bell(settings, rings, "reloaded")
+        } else {
+            if settings.clear {
+                screen::clear()?;
+            }
+            scrollback::start_run();
+            self.build = Some(Build::spawn(settings, registry, changed)?);
+            title(settings, "building")
+        }
+    }
+
+    /// Start the command again, or signal it to reload itself with a reload signal
+    fn restart(
+        &mut self,
+        settings: &Settings,
+        registry: &Registry,
+        changed: Option<&Path>,
+    ) -> Result<()> {
+        if settings.command_for(changed).is_empty() {
+            let message = "[SKIPPED] the command is just {file}, and no file changed";
+            message!("{}", settings.theme.status.paint(message));
+            return Ok(());
+        }
+        if let Some(signal) = settings.reload_signal {
+            if self.send(settings, signal)? {
+                return Ok(());
+            }
+        }
+        if let Some(mut process) = self.process.take() {
+            process.deregister(registry)?;
+            if settings.overlap && self.previous.is_none() && process.try_wait()?.is_none() {
+                self.previous = Some(process);
+                self.previous_started = self.started;
+            } else {
+                self.stop_later(settings, process)?;
+            }
+        }
+        // the old command's sockets and files are released once it exited and `restart-delay`
+        // passed
+        let released = self.released_at.filter(|&at| at > Instant::now());
+        if !self.stopping.is_empty() || released.is_some() {
+            self.restart_at = Some(released.unwrap_or_else(Instant::now));
+            self.restart_for = changed.map(Path::to_owned);
+            return title(settings, "restarting");
+        }
+        if mem::take(&mut self.clear) {
+            screen::clear()?;
+        }
+        scrollback::start_run();
+        let mut process = Process::spawn(settings, &self.listeners, changed)?;
+        process.register(registry)?;
+        self.process = Some(process);
+        title(settings, "running")?;
+        self.paused = false;
+        self.ready = false;
+        self.readiness = Some(Readiness::new(&settings.ready));
+        self.output_at = Some(Instant::now());
+        self.sampled_at = None;
+        self.cpu_ticks = None;
+        self.busy_since = None;
+        self.restart_at = None;
+        self.started = Some(Instant::now());
+        self.launches += 1;
+        self.record_pids(settings)
+    }
+
+    /// Send a signal to the command and the processes it started, returning whether it was
+    /// running
+    fn send(&mut self, settings: &Settings, signal: Signal) -> Result<bool> {
+        let Some(process) = &mut self.process else {
+            return Ok(false);
+        };
+        if process.try_wait()?.is_some() {
+            return Ok(false);
+        }
+        signal.send_group(process.id())?;
+        let message = format!("[SENT {}]", signal);
+        message!("{}", settings.theme.status.paint(message));
+        Ok(true)
+    }
+
+    /// What a key typed with `interactive` means: an action when it follows the escape key,
+    /// and otherwise input for the command
+    fn key(&mut self, settings: &Settings, event: &KeyEvent) -> Result<Option<Action>> {
+        let escape = settings.escape_key.matches(event);
+        if self.controls {
+            // the escape key passes keys on to the command again
+            return Ok(if escape {
+                Some(Action::Controls)
+            } else {
+                settings.keymap.action(event)
+            });
+        }
+        if mem::take(&mut self.escaped) {
+            if !escape {
+                return Ok(settings.keymap.action(event));
+            }
+        } else if escape {
+            self.escaped = true;
+            return Ok(None);
+        }
+        self.input(settings, event)?;
+        Ok(None)
+    }
+
+    /// Pass a key on to the command, which a pseudo-terminal gets as typed and a pipe line by
+    /// line
+    fn input(&mut self, settings: &Settings, event: &KeyEvent) -> Result<()> {
+        let Some(process) = &mut self.process else {
+            return Ok(());
+        };
+        let end = event.code == KeyCode::Char('d') && event.modifiers == KeyModifiers::CONTROL;
+        let bytes = if settings.pty {
+            keys::encode(event)
+        } else if end && self.line.is_empty() {
+            // the end of input, like a terminal
+            process.stdin = None;
+            None
+        } else {
+            edit_line(&mut self.line, event)?
+        };
+        match bytes {
+            Some(bytes) => process.send(&bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Pass pasted text on to the command, marked as pasted for a pseudo-terminal as a terminal
+    /// marks it, and as if typed for a pipe
+    fn paste(&mut self, settings: &Settings, text: &str) -> Result<()> {
+        // pasted text is never one of hot's keys
+        self.escaped = false;
+        let Some(process) = &mut self.process else {
+            return Ok(());
+        };
+        if settings.pty {
+            return process.send(format!("\x1b[200~{}\x1b[201~", text).as_bytes());
+        }
+        let mut bytes = Vec::new();
+        for c in text.replace("\r\n", "\n").chars() {
+            let code = match c {
+                '\r' | '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            let event = KeyEvent::new(code, KeyModifiers::NONE);
+            if let Some(line) = edit_line(&mut self.line, &event)? {
+                bytes.extend(line);
+            }
+        }
+        process.send(&bytes)
+    }
+
+    /// Pass on a new size of the terminal hot runs in to the commands
+    fn resize(&self) -> Result<()> {
+        let Some(size) = pty::window_size() else {
+            return Ok(());
+        };
+        for process in [&self.process, &self.previous].into_iter().flatten() {
+            process.resize(size)?;
+        }
+        Ok(())
+    }
 
-        if let Some(exit_status) = process.try_wait()? {
-            std::process::exit(exit_status.code().unwrap_or(11));
+    /// Update the pidfile with the commands running now
+    fn record_pids(&self, settings: &Settings) -> Result<()> {
+        let Some(path) = &settings.pidfile else {
+            return Ok(());
+        };
+        let groups = [&self.process, &self.previous]
+            .into_iter()
+            .flatten()
+            .chain(&self.stopping)
+            .map(|process| process.id())
+            .collect::<Vec<_>>();
+        pidfile::write(path, &groups)
+    }
+
+    /// Stop the command replaced by an overlapped restart once the new one is ready, copying
+    /// its output until then
+    fn retire_previous(&mut self, settings: &Settings, pipe: &mut Pipe) -> Result<()> {
+        let Some(previous) = &mut self.previous else {
+            return Ok(());
+        };
+        if !backed_up(settings) {
+            previous.drain(pipe, settings)?;
+        }
+        let ready = match &mut self.process {
+            Some(process) => self.ready && process.try_wait()?.is_none(),
+            None => false,
+        };
+        if ready {
+            let message = "[SWITCHED] the new command is ready, stopping the old one";
+            message!("{}", settings.theme.status.paint(message));
+        }
+        if ready || previous.try_wait()?.is_some() {
+            if let Some(previous) = self.previous.take() {
+                self.stop_later(settings, previous)?;
+            }
+            self.record_pids(settings)?;
+        }
+        Ok(())
+    }
+
+    /// Copy output from a stream of the command until none is left, or until `Pipe::BURST`
+    /// was copied, returning whether there may be more, so one stream can't hold up the rest
+    fn forward(&mut self, settings: &Settings, pipe: &mut Pipe, stream: Stream) -> Result<bool> {
+        let mut copied = 0;
+        while copied < Pipe::BURST {
+            let Some(process) = &mut self.process else {
+                return Ok(false);
+            };
+            if let Some(moved) = process.splice(stream, settings)? {
+                if moved == 0 {
+                    return Ok(false);
+                }
+                self.output_at = Some(Instant::now());
+                copied += moved;
+                continue;
+            }
+            let bytes = process.transfer(pipe, stream, settings)?;
+            if bytes.is_empty() {
+                return Ok(false);
+            }
+            copied += bytes.len();
+            self.output(settings, bytes);
+        }
+        Ok(true)
+    }
+
+    /// Copy the output the ring read, and start reading again
+    #[cfg(feature = "io-uring")]
+    fn forward_ring(&mut self, settings: &Settings) -> Result<()> {
+        let Some(process) = &mut self.process else {
+            return Ok(());
+        };
+        let Some(ring) = &mut process.ring else {
+            return Ok(());
+        };
+        while let Some((stream, bytes)) = ring.next()? {
+            copy_output(
+                &mut process.lines,
+                process.child.id(),
+                stream,
+                bytes,
+                settings,
+            )?;
+            // as `output` does, with the output still in the ring
+            if !bytes.is_empty() {
+                self.output_at = Some(Instant::now());
+            }
+            if let Some(readiness) = &mut self.readiness {
+                readiness.output(&settings.ready, bytes);
+            }
+        }
+        ring.submit()
+    }
+
+    /// Write partial lines the commands and the build wrote a while ago, like prompts, with
+    /// `line-buffered`, and how many lines were suppressed with `max-lines-per-sec`
+    fn flush_lines(&mut self, settings: &Settings) -> Result<()> {
+        for process in [&mut self.process, &mut self.previous]
+            .into_iter()
+            .flatten()
+        {
+            process.flush_lines(settings, output::Lines::DELAY)?;
+        }
+        match &mut self.build {
+            Some(build) => build.flush_lines(settings, output::Lines::DELAY),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy output from the build, returning whether there may be more
+    fn forward_build(&mut self, settings: &Settings, pipe: &mut Pipe) -> Result<bool> {
+        match &mut self.build {
+            Some(build) => build.forward(settings, pipe),
+            None => Ok(false),
+        }
+    }
+
+    /// Look for lines the probes wait for in output of the command
+    fn output(&mut self, settings: &Settings, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            self.output_at = Some(Instant::now());
+        }
+        if let Some(readiness) = &mut self.readiness {
+            readiness.output(&settings.ready, bytes);
+        }
+    }
+
+    /// Find out whether the command became ready, by passing its probes or by running for
+    /// `ready-delay` without any
+    fn check_ready(&mut self, settings: &Settings) -> Result<()> {
+        let (Some(process), Some(started)) = (&mut self.process, self.started) else {
+            return Ok(());
+        };
+        if self.ready || process.try_wait()?.is_some() {
+            return Ok(());
+        }
+        if settings.ready.is_empty() {
+            self.ready = started.elapsed() >= settings.ready_delay;
+        } else if self
+            .readiness
+            .as_mut()
+            .is_some_and(|readiness| readiness.check(&settings.ready))
+        {
+            self.ready = true;
+            self.readiness = None;
+            let elapsed = Duration::from_millis(started.elapsed().as_millis() as u64);
+            let message = format!("[READY] after {:?}", elapsed);
+            message!("{}", settings.theme.status.paint(message));
+        }
+        Ok(())
+    }
+
+    /// Go back to the command replaced by an overlapped restart when the new one exited
+    /// before it was ready, returning whether there was one to go back to
+    fn roll_back(
+        &mut self,
+        settings: &Settings,
+        registry: &Registry,
+        status: ExitStatus,
+    ) -> Result<bool> {
+        let Some(mut previous) = self.previous.take() else {
+            return Ok(false);
+        };
+        self.remove_exited(settings, registry)?;
+        previous.register(registry)?;
+        self.process = Some(previous);
+        self.started = self.previous_started;
+        self.ready = true;
+        self.readiness = None;
+        let message = format!(
+            "[ROLLBACK] the new command {} before it was ready, keeping the old one",
+            exit_state(status)
+        );
+        message!("{}", settings.theme.alert.paint(message));
+        self.record_pids(settings)?;
+        Ok(true)
+    }
+
+    /// Freeze the command and the processes it started, or let them carry on when they were
+    fn toggle_pause(&mut self, settings: &Settings) -> Result<()> {
+        let Some(process) = &self.process else {
+            return Ok(());
+        };
+        self.paused = !self.paused;
+        let (signal, message, state) = if self.paused {
+            (Signal::STOP, "[PAUSED]", "paused")
+        } else {
+            (Signal::CONT, "[RESUMED]", "running")
+        };
+        signal.send_group(process.id())?;
+        message!("{}", settings.theme.status.paint(message));
+        title(settings, state)
+    }
+
+    /// Stop the command and the build, which stay stopped, so changes don't start them again
+    /// either, until the command is reloaded
+    fn hold(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        if let Some(build) = self.build.take() {
+            build.cancel(settings)?;
+        }
+        if let Some(previous) = self.previous.take() {
+            self.stop_later(settings, previous)?;
+        }
+        self.remove_exited(settings, registry)?;
+        self.restart_at = None;
+        self.paused = false;
+        self.stopped = true;
+        let message = format!(
+            "[STOPPED] press {} to start the command again",
+            settings.key_for(Action::Reload)
+        );
+        message!("{}", settings.theme.status.paint(message));
+        title(settings, "stopped")
+    }
+
+    /// Show the PID of the command, how long it has been running, how often it was restarted
+    /// and how it exited last
+    fn info(&mut self, settings: &Settings) -> Result<()> {
+        let running = match &mut self.process {
+            Some(process) => process.try_wait()?.is_none().then(|| process.id()),
+            None => None,
+        };
+        let mut parts = vec![match (running, self.started) {
+            (Some(pid), Some(started)) => {
+                let state = if self.paused { "paused" } else { "running" };
+                format!("pid {}, {} for {}", pid, state, uptime(started.elapsed()))
+            }
+            _ if self.stopped => "stopped".to_owned(),
+            _ if self.build.is_some() => "building".to_owned(),
+            _ if self.restart_at.is_some() => "restarting".to_owned(),
+            _ => "not running".to_owned(),
+        }];
+        parts.push(format!(
+            "restarted {} times",
+            self.launches.saturating_sub(1)
+        ));
+        if let Some(status) = self.last_exit {
+            parts.push(format!("last {}", exit_state(status)));
+        }
+        let message = format!("[INFO] {}", parts.join(", "));
+        message!("{}", settings.theme.status.paint(message));
+        Ok(())
+    }
+
+    /// Write the output of the run of the command or the build which ended last again, between
+    /// markers
+    fn replay(&mut self, settings: &Settings) -> Result<()> {
+        let running = match &mut self.process {
+            Some(process) => process.try_wait()?.is_none(),
+            None => false,
+        };
+        let Some(mut output) = scrollback::run(running || self.build.is_some()) else {
+            let message = match settings.kept_lines() {
+                Some(_) => "[NO REPLAY] no run ended yet",
+                None => "[NO SCROLLBACK] start hot with --scrollback to keep output to replay",
+            };
+            message!("{}", settings.theme.status.paint(message));
+            return Ok(());
+        };
+        if !output.is_empty() && !output.ends_with(b"\n") {
+            output.push(b'\n');
+        }
+        let lines = output.iter().filter(|&&byte| byte == b'\n').count();
+        let start = format!("[REPLAY] the last run, {} lines", lines);
+        let mut bytes = format!("{}\n", settings.theme.status.paint(start)).into_bytes();
+        bytes.extend(output);
+        let end = settings.theme.status.paint("[END OF REPLAY]");
+        bytes.extend(format!("{}\n", end).bytes());
+        scrollback::write(bytes)
+    }
+
+    /// Show the signals which can be sent to the command, by the key picking each
+    fn signal_menu(&mut self) -> Result<()> {
+        let mut lines = vec!["Send the command".to_owned(), String::new()];
+        for (key, signal) in signal::MENU {
+            lines.push(format!("  {}  {}", key, signal));
+        }
+        scrollback::overlay(
+            lines,
+            "press the key of a signal, or another key to go back",
+        )?;
+        self.picking_signal = scrollback::overlaid();
+        Ok(())
+    }
+
+    /// Send the command the signal a key picks in the signal menu, if it picks one
+    fn pick_signal(&mut self, settings: &Settings, event: &KeyEvent) -> Result<()> {
+        let KeyCode::Char(c) = event.code else {
+            return Ok(());
+        };
+        let Some(&(_, signal)) = signal::MENU.iter().find(|(key, _)| *key == c) else {
+            return Ok(());
+        };
+        if !self.send(settings, signal)? {
+            let message = format!("[NOT SENT] {}, the command isn't running", signal);
+            message!("{}", settings.theme.status.paint(message));
+        }
+        Ok(())
+    }
+
+    /// Start editing the command line, with every key read as it is typed
+    fn edit_command(&mut self, settings: &Settings) -> Result<()> {
+        pty::keep_input()?;
+        let history = self
+            .history
+            .iter()
+            .filter(|&command| *command != settings.command)
+            .map(|command| settings::quote_command(command))
+            .collect();
+        self.editing = Some(Editor::new(&settings.command_line(), history));
+        self.show_editor(settings, None)
+    }
+
+    /// Stop editing the command line, giving the terminal back its own handling of keys unless
+    /// they are passed on to the command
+    fn stop_editing(&mut self, settings: &Settings) -> Result<()> {
+        self.editing = None;
+        if !settings.interactive {
+            pty::release_input()?;
+        }
+        scrollback::close_overlay()
+    }
+
+    /// Show the command line being edited over the output, with what is wrong with it
+    fn show_editor(&mut self, settings: &Settings, error: Option<&str>) -> Result<()> {
+        let Some(editor) = &self.editing else {
+            return Ok(());
+        };
+        let mut lines = vec![
+            "Edit the command, which runs from the next restart on".to_owned(),
+            String::new(),
+            format!("  {}", editor.render()),
+        ];
+        if let Some(error) = error {
+            lines.extend([String::new(), format!("  {}", error)]);
+        }
+        scrollback::overlay(lines, "enter to use it, esc to go back")?;
+        if !scrollback::overlaid() {
+            // no terminal to edit it in
+            self.stop_editing(settings)?;
+        }
+        Ok(())
+    }
+
+    /// Edit the command line as a key does, returning the command once enter is pressed
+    fn edit_key(&mut self, settings: &Settings, event: &KeyEvent) -> Result<Option<Vec<String>>> {
+        let Some(editor) = &mut self.editing else {
+            return Ok(None);
+        };
+        let line = match editor.key(event) {
+            Edit::Editing => return self.show_editor(settings, None).map(|_| None),
+            Edit::Cancelled => return self.stop_editing(settings).map(|_| None),
+            Edit::Done(line) => line,
+        };
+        let command = match config::split_words(&line) {
+            Ok(words) if words.is_empty() => Err("the command is empty".to_owned()),
+            result => result,
+        };
+        let command = match command {
+            Ok(command) => command,
+            Err(err) => return self.show_editor(settings, Some(&err)).map(|_| None),
+        };
+        self.stop_editing(settings)?;
+        self.remember(&settings.command);
+        self.remember(&command);
+        let message = format!(
+            "[COMMAND] {}, press {} to restart with it",
+            settings::quote_command(&command),
+            settings.key_for(Action::Reload)
+        );
+        message!("{}", settings.theme.status.paint(message));
+        Ok(Some(command))
+    }
+
+    /// Start searching the output kept, with every key read as it is typed
+    fn start_search(&mut self, settings: &Settings) -> Result<()> {
+        if !scrollback::start_search()? {
+            let message = "[NO SCROLLBACK] start hot with --scrollback to keep output to search";
+            message!("{}", settings.theme.status.paint(message));
+            return Ok(());
+        }
+        pty::keep_input()?;
+        self.query = Some(Editor::new("", Vec::new()));
+        Ok(())
+    }
+
+    /// Search as a key edits the query, until enter or escape ends typing it
+    fn search_key(&mut self, settings: &Settings, event: &KeyEvent) -> Result<()> {
+        let Some(editor) = &mut self.query else {
+            return Ok(());
+        };
+        let keep = match editor.key(event) {
+            Edit::Editing => return scrollback::search(&editor.line(), editor.render()),
+            Edit::Done(query) => !query.is_empty(),
+            Edit::Cancelled => false,
+        };
+        self.query = None;
+        if !settings.interactive {
+            pty::release_input()?;
+        }
+        scrollback::end_search(keep)
+    }
+
+    /// Insert pasted text into the query being typed
+    fn search_paste(&mut self, text: &str) -> Result<()> {
+        match &mut self.query {
+            Some(editor) => {
+                editor.insert(text);
+                scrollback::search(&editor.line(), editor.render())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Move between the matches of the search shown as a key does, returning the action of
+    /// other keys
+    fn browse(&mut self, settings: &Settings, event: &KeyEvent) -> Result<Option<Action>> {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Char('n') if !ctrl => scrollback::next_match(true)?,
+            KeyCode::Char('N') if !ctrl => scrollback::next_match(false)?,
+            KeyCode::Esc => scrollback::end_search(false)?,
+            _ if settings.interactive => return self.key(settings, event),
+            _ => return Ok(settings.keymap.action(event)),
+        }
+        Ok(None)
+    }
+
+    /// Add a command line to the history as the latest one, which is in use
+    fn remember(&mut self, command: &[String]) {
+        self.history.retain(|used| used != command);
+        self.history.push(command.to_vec());
+        self.history_at = self.history.len() - 1;
+    }
+
+    /// The command line used before the one in use, going round to the latest after the first
+    fn previous_command(&mut self) -> Option<Vec<String>> {
+        if self.history.len() < 2 {
+            return None;
+        }
+        self.history_at = self
+            .history_at
+            .checked_sub(1)
+            .unwrap_or(self.history.len() - 1);
+        Some(self.history[self.history_at].clone())
+    }
+
+    /// Insert pasted text into the command line being edited
+    fn edit_paste(&mut self, settings: &Settings, text: &str) -> Result<()> {
+        if let Some(editor) = &mut self.editing {
+            editor.insert(text);
+        }
+        self.show_editor(settings, None)
+    }
+
+    /// Switch between taking keys as hot's own and passing them on to the command, with
+    /// `interactive`
+    fn toggle_controls(&mut self, settings: &Settings) {
+        if !settings.interactive {
+            return;
+        }
+        self.controls = !self.controls;
+        let message = if self.controls {
+            "[CONTROLS]"
+        } else {
+            "[PASSTHROUGH]"
+        };
+        message!("{}", settings.theme.status.paint(message));
+    }
+
+    /// Start the command again after it exited, once the backoff delay has passed
+    ///
+    /// The delay doubles for every exit up to `backoff-max`, after which it starts over when
+    /// the command ran for longer than that.
+    fn schedule_restart(&mut self, settings: &Settings, registry: &Registry) -> Result<Duration> {
+        self.remove_exited(settings, registry)?;
+        let healthy = self
+            .started
+            .is_some_and(|started| started.elapsed() >= settings.backoff_max);
+        let delay = match self.backoff {
+            Some(backoff) if !healthy => (backoff * 2).min(settings.backoff_max),
+            _ => settings.backoff,
+        };
+        self.backoff = Some(delay);
+        self.restarts += 1;
+        self.restart_at = Some(Instant::now() + delay);
+        self.restart_for = None;
+        Ok(delay)
+    }
+
+    /// Stop the command and any build before exiting
+    fn stop(&mut self, settings: &Settings) -> Result<()> {
+        self.stop_with(settings, settings.stop_signal)
+    }
+
+    fn stop_with(&mut self, settings: &Settings, signal: Signal) -> Result<()> {
+        if let Some(build) = self.build.take() {
+            build.cancel(settings)?;
+        }
+        for mut process in self
+            .previous
+            .take()
+            .into_iter()
+            .chain(mem::take(&mut self.stopping))
+        {
+            process.stop_with(settings, signal)?;
+        }
+        if let Some(process) = &mut self.process {
+            process.stop_with(settings, signal)?;
+        }
+        match &settings.pidfile {
+            Some(path) => pidfile::remove(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Reap processes orphaned by the command which exited, as hot is their subreaper, or
+    /// PID 1 in a container
+    fn reap_orphans(&self) {
+        signal::reap_orphans(&self.children());
+    }
+
+    /// The PIDs of the commands and the build, each leading a process group of its own
+    fn children(&self) -> Vec<u32> {
+        [&self.process, &self.previous]
+            .into_iter()
+            .flatten()
+            .chain(&self.stopping)
+            .map(|process| process.id())
+            .chain(self.build.as_ref().map(|build| build.child.id()))
+            .collect()
+    }
+
+    /// Stop the commands and the build along with hot itself, until hot is continued like
+    /// with `fg`
+    fn suspend(&mut self, settings: &Settings) -> Result<()> {
+        for child in self.children() {
+            Signal::STOP.send_group(child)?;
+        }
+        message!("{}", settings.theme.status.paint("[SUSPENDED]"));
+        // the terminal is only in raw mode while reading keys, so the shell gets it back as it
+        // was, and raw mode is enabled again for the next key
+        screen::release()?;
+        output::lend();
+        unsafe { libc::raise(libc::SIGSTOP) };
+        output::reclaim();
+        screen::resume()?;
+        if !self.paused {
+            for child in self.children() {
+                Signal::CONT.send_group(child)?;
+            }
+        }
+        // time spent suspended isn't silence
+        if self.output_at.is_some() {
+            self.output_at = Some(Instant::now());
+        }
+        message!("{}", settings.theme.status.paint("[CONTINUED]"));
+        Ok(())
+    }
+
+    /// Forget the command after it exited, stopping what it left running
+    fn remove_exited(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        if let Some(process) = self.process.take() {
+            process.deregister(registry)?;
+            self.stop_later(settings, process)?;
+        }
+        Ok(())
+    }
+
+    /// Whether commands were asked to exit and didn't yet
+    fn stopping(&self) -> bool {
+        !self.stopping.is_empty()
+            || self
+                .process
+                .as_ref()
+                .is_some_and(|process| process.stopping.is_some())
+    }
+
+    /// Ask a command to exit without waiting for it, which `check_stopping` then follows
+    fn stop_later(&mut self, settings: &Settings, mut process: Process) -> Result<()> {
+        if process.start_stop(settings, settings.stop_signal)? {
+            self.released_at = Some(Instant::now() + settings.restart_delay);
+        } else {
+            self.stopping.push(process);
+        }
+        Ok(())
+    }
+
+    /// Copy the output of the commands asked to exit and forget those which did, killing those
+    /// which didn't in time, along with the command when it ran for longer than `timeout`
+    fn check_stopping(&mut self, settings: &Settings, pipe: &mut Pipe) -> Result<()> {
+        if let Some(process) = &mut self.process {
+            process.check_stop(settings, pipe)?;
+        }
+        let count = self.stopping.len();
+        let mut stopping = Vec::with_capacity(count);
+        for mut process in mem::take(&mut self.stopping) {
+            if !process.check_stop(settings, pipe)? {
+                stopping.push(process);
+            }
+        }
+        self.stopping = stopping;
+        if self.stopping.len() == count {
+            return Ok(());
+        }
+        let released = Instant::now() + settings.restart_delay;
+        self.released_at = Some(released);
+        // a restart waiting for them waits for `restart-delay` from now on
+        if let Some(at) = &mut self.restart_at {
+            *at = (*at).max(released);
+        }
+        self.record_pids(settings)
+    }
+
+    /// Whether the command exited shortly after starting `crash-count` times in a row
+    fn crash_looping(&mut self, settings: &Settings) -> bool {
+        let quick = self
+            .started
+            .is_some_and(|started| started.elapsed() < settings.crash_window);
+        self.quick_exits = if quick { self.quick_exits + 1 } else { 0 };
+        self.quick_exits >= settings.crash_count
+    }
+
+    /// Count a run towards `times`, returning whether that many ran
+    fn count_run(&mut self, settings: &Settings, status: ExitStatus) -> bool {
+        self.runs += 1;
+        if !status.success() {
+            self.failures += 1;
+            self.failure = Some(status);
+        }
+        settings.times.is_some_and(|times| self.runs >= times)
+    }
+
+    /// Start the command when a scheduled restart is due, and the commands asked to exit did
+    fn restart_if_due(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        match self.restart_at {
+            Some(at) if at <= Instant::now() && self.stopping.is_empty() => {
+                let changed = self.restart_for.take();
+                self.restart(settings, registry, changed.as_deref())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop the command when it has been running for longer than `timeout`, its exit is then
+    /// handled like any other
+    fn stop_if_timed_out(&mut self, settings: &Settings) -> Result<()> {
+        let (Some(timeout), Some(started), Some(process)) =
+            (settings.timeout, self.started, &mut self.process)
+        else {
+            return Ok(());
+        };
+        if started.elapsed() < timeout
+            || process.stopping.is_some()
+            || process.try_wait()?.is_some()
+        {
+            return Ok(());
+        }
+        let message = format!("[TIMEOUT] the command ran for longer than {:?}", timeout);
+        message!("{}", settings.theme.status.paint(message));
+        process.start_stop(settings, settings.stop_signal)?;
+        Ok(())
+    }
+
+    /// Restart the command when it didn't write any output for `stall-timeout`
+    fn restart_if_stalled(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        let (Some(stall_timeout), Some(output_at), Some(process)) =
+            (settings.stall_timeout, self.output_at, &mut self.process)
+        else {
+            return Ok(());
+        };
+        if self.paused || output_at.elapsed() < stall_timeout || process.try_wait()?.is_some() {
+            return Ok(());
+        }
+        let message = format!(
+            "[STALLED] the command wrote no output for {:?}, restarting",
+            stall_timeout
+        );
+        message!("{}", settings.theme.status.paint(message));
+        self.restart(settings, registry, None)
+    }
+
+    /// Restart the command when its processes use more memory than `max-memory`, or more CPU
+    /// than `max-cpu` for `max-cpu-for`
+    fn restart_if_overusing(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        let Some(process) = &mut self.process else {
+            return Ok(());
+        };
+        let watched = settings.max_memory.is_some() || settings.max_cpu.is_some();
+        let sampled = self
+            .sampled_at
+            .is_some_and(|at| at.elapsed() < Self::SAMPLE_INTERVAL);
+        if !watched || sampled || process.try_wait()?.is_some() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let previous = self.sampled_at.replace(now);
+        let usage = proc::group_usage(process.id());
+        let mut overuse = None;
+        if let Some(max) = settings.max_memory {
+            let memory = Size(usage.memory);
+            if memory > max {
+                overuse = Some((
+                    "MEMORY",
+                    format!("uses {} of memory, more than {}", memory, max),
+                ));
+            }
+        }
+        if let (Some(max), Some(at), Some(ticks)) = (settings.max_cpu, previous, self.cpu_ticks) {
+            let seconds = usage.cpu_ticks.saturating_sub(ticks) as f64 / proc::ticks_per_second();
+            let cpu = seconds / (now - at).as_secs_f64() * 100.0;
+            if cpu <= max.0 {
+                self.busy_since = None;
+            } else if self.busy_since.get_or_insert(at).elapsed() >= settings.max_cpu_for {
+                let detail = format!("used more than {} CPU for {:?}", max, settings.max_cpu_for);
+                overuse = Some(("CPU", detail));
+            }
+        }
+        self.cpu_ticks = Some(usage.cpu_ticks);
+        let Some((tag, detail)) = overuse else {
+            return Ok(());
+        };
+        let message = format!("[{}] the command {}, restarting", tag, detail);
+        message!("{}", settings.theme.status.paint(message));
+        self.restart(settings, registry, None)
+    }
+
+    /// The exit status of the command, if it exited by itself
+    fn exited(&mut self) -> Result<Option<ExitStatus>> {
+        match &mut self.process {
+            Some(process) => process.try_wait(),
+            None => Ok(None),
+        }
+    }
+
+    /// Restart the command once the build finished successfully, the command keeps running
+    /// when it failed
+    fn finish_build(&mut self, settings: &Settings, registry: &Registry) -> Result<()> {
+        let status = match &mut self.build {
+            Some(build) => match build.child.try_wait()? {
+                Some(status) => status,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let Some(mut build) = self.build.take() else {
+            return Ok(());
+        };
+        let changed = build.changed.take();
+        build.finish(settings)?;
+        if status.success() {
+            let reloaded = self.started.is_some();
+            self.restart(settings, registry, changed.as_deref())?;
+            let rings = reloaded && settings.bell.contains(&Bell::Reload);
+            bell(settings, rings, "reloaded")
+        } else {
+            let message = format!("[BUILD FAILED] {}", exit_state(status));
+            message!("{}", settings.theme.status.paint(message));
+            title(settings, "build failed")
+        }
+    }
+}
+
+/// Edit the line typed for a command reading a pipe, echoing it as the terminal would, and
+/// return it once enter is pressed
+fn edit_line(line: &mut String, event: &KeyEvent) -> Result<Option<Vec<u8>>> {
+    match event.code {
+        KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+            line.push(c);
+            output::write(Stream::Stdout, c.to_string().as_bytes())?;
+        }
+        KeyCode::Backspace if line.pop().is_some() => output::write(Stream::Stdout, b"\x08 \x08")?,
+        KeyCode::Enter => {
+            line.push('\n');
+            output::write(Stream::Stdout, b"\n")?;
+            return Ok(Some(mem::take(line).into_bytes()));
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// Exit like the command did, with 128 and the number of the signal when a signal killed it
+/// or by the same signal with `reraise-signal`
+fn exit_like(status: ExitStatus, settings: &Settings) -> ! {
+    if let Some(signal) = status.signal() {
+        if settings.reraise_signal {
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+        std::process::exit(128 + signal);
+    }
+    std::process::exit(status.code().unwrap_or(1))
+}
+
+/// Stop the commands a hot which didn't exit cleanly left running, so they don't keep holding
+/// on to ports and locks
+fn stop_stale(settings: &Settings) -> Result<()> {
+    let Some(path) = &settings.pidfile else {
+        return Ok(());
+    };
+    let Some((hot, groups)) = pidfile::read(path)? else {
+        return Ok(());
+    };
+    if hot != std::process::id() && signal::is_running(hot) {
+        message!(
+            "hot: warning: {}: hot is already running as PID {}",
+            path.display(),
+            hot
+        );
+        return Ok(());
+    }
+    for group in groups {
+        if !signal::group_exists(group) {
+            continue;
+        }
+        let message = format!(
+            "[STALE] stopping the command left running by a previous hot (PID {})",
+            group
+        );
+        message!("{}", settings.theme.status.paint(message));
+        settings.stop_signal.send_group(group)?;
+        Signal::CONT.send_group(group)?;
+        let deadline = Instant::now() + settings.kill_timeout;
+        while Instant::now() < deadline && signal::group_exists(group) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if signal::group_exists(group) {
+            Signal::KILL.send_group(group)?;
+        }
+    }
+    Ok(())
+}
+
+/// Wait for the services the command depends on to accept connections, trying again with
+/// backoff
+fn wait_for(settings: &Settings, received: &AtomicUsize) {
+    for dependency in &settings.wait_for {
+        let mut delay = settings.backoff;
+        let mut waited = false;
+        while !dependency.is_up() {
+            if !waited {
+                let message = format!("[WAITING] for {}", dependency);
+                message!("{}", settings.theme.status.paint(message));
+                waited = true;
+            }
+            let until = Instant::now() + delay;
+            while Instant::now() < until {
+                if received.load(Ordering::Relaxed) != 0 {
+                    std::process::exit(2);
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            delay = (delay * 2).min(settings.backoff_max);
+        }
+        if waited {
+            let message = format!("[UP] {}", dependency);
+            message!("{}", settings.theme.status.paint(message));
+        }
+    }
+}
+
+fn watch(settings: &Settings, registry: &Registry) -> Result<Option<Watcher>> {
+    let paths = settings.watch_paths();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let watcher = Watcher::new(
+        &paths,
+        &settings.ignore,
+        settings.gitignore,
+        settings.poll_watch,
+        &settings.events,
+    )?;
+    watcher.register(registry, WATCH)?;
+    Ok(Some(watcher))
+}
+
+fn run() -> Result<()> {
+    let matches = cli::parse_args();
+    if let Some(sub) = matches.subcommand {
+        return subcommand(sub, &matches);
+    }
+    let mut settings = Settings::resolve(&Config::load(&matches)?)?;
+    if settings.command.is_empty() {
+        println!("{}", cli::usage());
+        std::process::exit(1);
+    }
+    // bound first, so the sockets are on the descriptors they are passed on as
+    let listeners = Listeners::bind(&settings.listen)?;
+    let mut poll = Poll::new()?;
+    let mut events = Events::with_capacity(128);
+
+    let mut pipe = Pipe::default();
+    let mut waiting = output::Waiting::default();
+    // streams of the command with output left after their last turn
+    let mut readable = Vec::<Stream>::new();
+    // whether the build has output left after its last turn
+    let mut build_readable = false;
+    // whether the ring read output which wasn't copied yet
+    #[cfg(feature = "io-uring")]
+    let mut ring_read = false;
+
+    let mut config_watcher = config::Watcher::new(&matches)?;
+    let mut watcher = watch(&settings, poll.registry())?;
+    signal::become_subreaper()?;
+    // the command is in a process group of its own, so signals sent to hot, including keys
+    // the terminal turns into signals, are passed on to it rather than leaving it orphaned.
+    // SIGHUP and SIGUSR1 let other tools reload instead, and SIGTSTP suspends hot along
+    // with the command.
+    let received = Arc::new(AtomicUsize::new(0));
+    let signals = [
+        libc::SIGINT,
+        libc::SIGQUIT,
+        libc::SIGTERM,
+        libc::SIGHUP,
+        libc::SIGUSR1,
+        libc::SIGTSTP,
+    ];
+    for signal in signals {
+        signal_hook::flag::register_usize(signal, Arc::clone(&received), signal as usize)?;
+    }
+    // kept apart, as resizing the terminal sends a signal for every step
+    let resized = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(libc::SIGWINCH, Arc::clone(&resized))?;
+    if settings.interactive {
+        pty::keep_input()?;
+    }
+    if settings.alt_screen {
+        screen::enter_alternate()?;
+    }
+    // a command reading the terminal shares it with hot, along with its flags
+    if !settings.command_reads_terminal() {
+        output::unblock();
+    }
+    // so pasted text isn't taken for hot's keys
+    if !settings.command_reads_terminal() {
+        screen::enable_paste()?;
+        if settings.mouse {
+            screen::enable_mouse()?;
+        }
+        if let Some(lines) = settings.kept_lines() {
+            scrollback::enable(lines);
+        }
+    }
+    stop_stale(&settings)?;
+    wait_for(&settings, &received);
+    let mut runner = Runner {
+        listeners,
+        ..Runner::default()
+    };
+    runner.reload(&settings, poll.registry(), None)?;
+    // changes wait until none have arrived for the debounce period
+    let mut changes = Vec::<watch::Change>::new();
+    let mut quiet_at = Instant::now();
+    let mut every_at = settings.every.map(|every| Instant::now() + every);
+    let mut cron_at = settings.cron.as_ref().and_then(cron::Schedule::next);
+    // the command line edited with the edit key, which a reloaded configuration doesn't undo
+    let mut edited = None::<Vec<String>>;
+    loop {
+        if config_watcher.changed() {
+            match Config::load(&matches).and_then(|config| Settings::resolve(&config)) {
+                Ok(new) => {
+                    message!("{}", new.theme.status.paint("[CONFIG RELOADED]"));
+                    if !new.watches_same(&settings) {
+                        if let Some(watcher) = watcher.take() {
+                            watcher.deregister(poll.registry())?;
+                        }
+                        watcher = watch(&new, poll.registry()).unwrap_or_else(|err| {
+                            message!("hot: {}", err);
+                            None
+                        });
+                    }
+                    if new.every != settings.every {
+                        every_at = new.every.map(|every| Instant::now() + every);
+                    }
+                    if new.cron != settings.cron {
+                        cron_at = new.cron.as_ref().and_then(cron::Schedule::next);
+                    }
+                    settings = new;
+                    if let Some(command) = &edited {
+                        settings.command = command.clone();
+                    }
+                }
+                Err(err) => message!("hot: {}", err),
+            }
+        }
+
+        if resized.swap(false, Ordering::Relaxed) {
+            runner.resize()?;
+            scrollback::redraw()?;
+        }
+        let action = match received.swap(0, Ordering::Relaxed) as libc::c_int {
+            0 if settings.command_reads_terminal() => None,
+            0 => match read_input()? {
+                Some(Event::Key(event)) if runner.editing.is_some() => {
+                    if let Some(command) = runner.edit_key(&settings, &event)? {
+                        settings.command = command.clone();
+                        edited = Some(command);
+                    }
+                    None
+                }
+                Some(Event::Paste(text)) if runner.editing.is_some() => {
+                    runner.edit_paste(&settings, &text)?;
+                    None
+                }
+                Some(Event::Key(event)) if runner.query.is_some() => {
+                    runner.search_key(&settings, &event)?;
+                    None
+                }
+                Some(Event::Paste(text)) if runner.query.is_some() => {
+                    runner.search_paste(&text)?;
+                    None
+                }
+                Some(Event::Key(event)) if scrollback::overlaid() => {
+                    scrollback::close_overlay()?;
+                    if mem::take(&mut runner.picking_signal) {
+                        runner.pick_signal(&settings, &event)?;
+                    }
+                    None
+                }
+                Some(Event::Key(event)) if scrollback::searched() => {
+                    runner.browse(&settings, &event)?
+                }
+                Some(Event::Key(event)) if settings.interactive => runner.key(&settings, &event)?,
+                Some(Event::Key(event)) => settings.keymap.action(&event),
+                Some(Event::Paste(text)) if settings.interactive => {
+                    runner.paste(&settings, &text)?;
+                    None
+                }
+                Some(Event::Mouse(event)) => {
+                    scrollback::scroll(match event.kind {
+                        MouseEventKind::ScrollUp => SCROLL_LINES,
+                        _ => -SCROLL_LINES,
+                    })?;
+                    None
+                }
+                _ => None,
+            },
+            libc::SIGHUP | libc::SIGUSR1 => Some(Action::Reload),
+            libc::SIGTSTP => Some(Action::Suspend),
+            // exit once the command did, as it would have without hot in between
+            signal => {
+                runner.stop_with(&settings, Signal(signal))?;
+                match runner.exited()? {
+                    Some(status) => exit_like(status, &settings),
+                    None => std::process::exit(128 + signal),
+                }
+            }
+        };
+        match action {
+            Some(Action::Reload) => {
+                message!("{}", settings.theme.reload.paint("[RELOAD]"));
+                runner.reload(&settings, poll.registry(), None)?;
+            }
+            Some(Action::Quit) => {
+                runner.stop(&settings)?;
+                std::process::exit(2)
+            }
+            Some(Action::Exit) => {
+                runner.stop(&settings)?;
+                match runner.exited()? {
+                    Some(status) => exit_like(status, &settings),
+                    None => std::process::exit(0),
+                }
+            }
+            Some(Action::Stop) => runner.hold(&settings, poll.registry())?,
+            Some(Action::Pause) => runner.toggle_pause(&settings)?,
+            Some(Action::Hold) => {
+                let message = if scrollback::toggle_hold()? {
+                    "[OUTPUT HELD]"
+                } else {
+                    "[OUTPUT RESUMED]"
+                };
+                message!("{}", settings.theme.status.paint(message));
+            }
+            Some(Action::Suspend) => runner.suspend(&settings)?,
+            Some(Action::Clear) => screen::clear()?,
+            Some(Action::Controls) => runner.toggle_controls(&settings),
+            Some(Action::Signal) => runner.signal_menu()?,
+            Some(Action::Edit) => runner.edit_command(&settings)?,
+            Some(Action::Info) => runner.info(&settings)?,
+            Some(Action::Replay) => runner.replay(&settings)?,
+            Some(Action::Copy) => copy_error(&settings)?,
+            Some(Action::PageUp) => scrollback::page(1)?,
+            Some(Action::PageDown) => scrollback::page(-1)?,
+            Some(Action::Top) => scrollback::top()?,
+            Some(Action::Bottom) => scrollback::bottom()?,
+            Some(Action::Search) => runner.start_search(&settings)?,
+            Some(Action::History) => match runner.previous_command() {
+                Some(command) => {
+                    settings.command = command.clone();
+                    edited = Some(command);
+                    let message = "[RELOAD] with the command used before";
+                    message!("{}", settings.theme.reload.paint(message));
+                    runner.reload(&settings, poll.registry(), None)?;
+                }
+                None => {
+                    let message = format!(
+                        "[NO HISTORY] edit the command with {} first",
+                        settings.key_for(Action::Edit)
+                    );
+                    message!("{}", settings.theme.status.paint(message));
+                }
+            },
+            Some(Action::Help) => scrollback::overlay(help(&settings), "press any key to go back")?,
+            None => {}
+        }
+
+        let mut timeout = Duration::from_millis(100);
+        // output hot's stdout or stderr didn't take yet, which wake the poll once they do
+        output::flush()?;
+        if waiting.update(poll.registry())? {
+            timeout = timeout.min(output::Waiting::RETRY);
+        }
+        let held = backed_up(&settings);
+        if (!readable.is_empty() || build_readable) && !held {
+            timeout = Duration::ZERO;
+        }
+        #[cfg(feature = "io-uring")]
+        if ring_read && !held {
+            timeout = Duration::ZERO;
+        }
+        if !changes.is_empty() {
+            timeout = timeout.min(quiet_at.saturating_duration_since(Instant::now()));
+        }
+        // a restart waiting for the commands asked to exit is due once they did
+        let restart_at = runner.restart_at.filter(|_| runner.stopping.is_empty());
+        for at in [restart_at, every_at, cron_at].into_iter().flatten() {
+            timeout = timeout.min(at.saturating_duration_since(Instant::now()));
+        }
+        if runner.stopping() {
+            timeout = timeout.min(Process::STOP_POLL);
+        }
+        if let Err(err) = poll.poll(&mut events, Some(timeout)) {
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+        for event in events.iter() {
+            let stream = match event.token() {
+                #[cfg(feature = "io-uring")]
+                Process::RING => {
+                    ring_read = true;
+                    continue;
+                }
+                Process::STDOUT if event.is_readable() => Stream::Stdout,
+                Process::STDERR if event.is_readable() => Stream::Stderr,
+                Build::STDOUT | Build::STDERR => {
+                    build_readable = true;
+                    continue;
+                }
+                _ => continue,
+            };
+            if !readable.contains(&stream) {
+                readable.push(stream);
+            }
+        }
+        events.clear();
+        if !held {
+            #[cfg(feature = "io-uring")]
+            if mem::take(&mut ring_read) {
+                runner.forward_ring(&settings)?;
+            }
+            // events only come when more output arrives, so streams are read until none is left
+            let mut more = Vec::new();
+            for stream in readable.drain(..) {
+                if runner.forward(&settings, &mut pipe, stream)? {
+                    more.push(stream);
+                }
+            }
+            readable = more;
+            if build_readable {
+                build_readable = runner.forward_build(&settings, &mut pipe)?;
+            }
+        }
+
+        // read even without a WATCH event, as the polling watcher has no way to wake us up
+        if let Some(watcher) = &mut watcher {
+            for change in watcher.read()? {
+                if !changes.iter().any(|seen| seen.path == change.path) {
+                    changes.push(change);
+                }
+                quiet_at = Instant::now() + settings.debounce;
+            }
+        }
+
+        if runner.stopped {
+            changes.clear();
+        }
+        if !changes.is_empty() && Instant::now() >= quiet_at {
+            let mut reason = changes[0].to_string();
+            if changes.len() > 1 {
+                reason.push_str(&format!(" (and {} more)", changes.len() - 1));
+            }
+            message!(
+                "{}",
+                settings.theme.reload.paint(format!("[RELOAD] {}", reason))
+            );
+            let changed = changes[0].display_path();
+            runner.reload(&settings, poll.registry(), Some(changed))?;
+            changes.clear();
+        }
+
+        if let (Some(every), Some(at)) = (settings.every, every_at) {
+            if at <= Instant::now() {
+                if !runner.stopped {
+                    let message = format!("[RELOAD] every {:?}", every);
+                    message!("{}", settings.theme.reload.paint(message));
+                    runner.reload(&settings, poll.registry(), None)?;
+                }
+                every_at = Some(Instant::now() + every);
+            }
+        }
+        if let (Some(schedule), Some(at)) = (&settings.cron, cron_at) {
+            if at <= Instant::now() {
+                if !runner.stopped {
+                    let message = format!("[RELOAD] cron {}", schedule);
+                    message!("{}", settings.theme.reload.paint(message));
+                    runner.reload(&settings, poll.registry(), None)?;
+                }
+                cron_at = schedule.next();
+            }
+        }
+
+        runner.reap_orphans();
+        runner.finish_build(&settings, poll.registry())?;
+        runner.restart_if_due(&settings, poll.registry())?;
+        runner.stop_if_timed_out(&settings)?;
+        runner.restart_if_stalled(&settings, poll.registry())?;
+        runner.restart_if_overusing(&settings, poll.registry())?;
+        runner.check_ready(&settings)?;
+        runner.retire_previous(&settings, &mut pipe)?;
+        runner.check_stopping(&settings, &mut pipe)?;
+        runner.flush_lines(&settings)?;
+        if let Some(exit_status) = runner.exited()? {
+            if let Some(process) = &mut runner.process {
+                process.drain(&mut pipe, &settings)?;
+            }
+            runner.last_exit = Some(exit_status);
+            let rings = settings.bell.contains(&Bell::Exit)
+                || !exit_status.success() && settings.bell.contains(&Bell::Crash);
+            bell(&settings, rings, &exit_state(exit_status))?;
+            if runner.roll_back(&settings, poll.registry(), exit_status)? {
+                continue;
+            }
+            if let Some(times) = settings.times {
+                if runner.count_run(&settings, exit_status) {
+                    let message = format!("[DONE] {} of {} runs failed", runner.failures, times);
+                    message!("{}", settings.theme.status.paint(message));
+                    runner.stop(&settings)?;
+                    match runner.failure {
+                        Some(status) => exit_like(status, &settings),
+                        None => std::process::exit(0),
+                    }
+                }
+                let message = format!(
+                    "[EXITED] {}, run {} of {}",
+                    exit_state(exit_status),
+                    runner.runs,
+                    times
+                );
+                message!("{}", settings.theme.status.paint(message));
+                runner.restart(&settings, poll.registry(), None)?;
+                continue;
+            }
+            let restart = settings.restart.applies_to(exit_status);
+            let exhausted = settings
+                .max_restarts
+                .is_some_and(|max| runner.restarts >= max);
+            if restart && exhausted {
+                let message = format!(
+                    "[GAVE UP] {} after {} restarts",
+                    exit_state(exit_status),
+                    runner.restarts
+                );
+                message!("{}", settings.theme.status.paint(message));
+                runner.stop(&settings)?;
+                exit_like(exit_status, &settings);
+            } else if restart && runner.crash_looping(&settings) {
+                runner.remove_exited(&settings, poll.registry())?;
+                let message = format!(
+                    "[CRASH LOOP] the command exited {} times within {:?} of starting, \
+                     press {} or change a file to start it again",
+                    runner.quick_exits,
+                    settings.crash_window,
+                    settings.key_for(Action::Reload)
+                );
+                message!("{}", settings.theme.alert.paint(message));
+                title(
+                    &settings,
+                    &format!("{}, crash loop", exit_state(exit_status)),
+                )?;
+            } else if restart {
+                let delay = runner.schedule_restart(&settings, poll.registry())?;
+                let message = format!(
+                    "[EXITED] {}, restarting in {:?}",
+                    exit_state(exit_status),
+                    delay
+                );
+                message!("{}", settings.theme.status.paint(message));
+                title(
+                    &settings,
+                    &format!("{}, restarting", exit_state(exit_status)),
+                )?;
+            } else {
+                runner.stop(&settings)?;
+                exit_like(exit_status, &settings);
+            }
         }
     }
 }
@@ -0,0 +1,200 @@
+//! Fresh Linux namespaces for the command, isolating its network, processes or mounts
+
+use std::{
+    ffi::CStr,
+    io::{self, Result},
+    mem,
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Namespace {
+    /// Only a loopback interface of its own
+    Net,
+    /// Process IDs of its own, with the command as PID 1
+    Pid,
+    /// Mounts of its own, which don't propagate back
+    Mount,
+}
+
+impl FromStr for Namespace {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "net" => Ok(Namespace::Net),
+            "pid" => Ok(Namespace::Pid),
+            "mount" | "mnt" => Ok(Namespace::Mount),
+            _ => Err(format!(
+                "unknown namespace '{}', expected net, pid or mount",
+                s
+            )),
+        }
+    }
+}
+
+fn check(result: libc::c_int) -> Result<libc::c_int> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(result)
+}
+
+/// Write a whole file, for the few files written between fork and exec
+fn write(path: &CStr, contents: &[u8]) -> Result<()> {
+    let fd = check(unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) })?;
+    let written = unsafe { libc::write(fd, contents.as_ptr().cast(), contents.len()) };
+    unsafe { libc::close(fd) };
+    if written < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The namespaces to run the command in, with everything that needs allocating prepared
+/// before fork
+#[derive(Clone, Debug)]
+pub struct Isolation {
+    namespaces: Vec<Namespace>,
+    /// Lines mapping the user and group to themselves in a user namespace of their own, which
+    /// lets users other than root create the rest
+    id_maps: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Isolation {
+    pub fn new(namespaces: &[Namespace]) -> Self {
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        Self {
+            namespaces: namespaces.to_vec(),
+            id_maps: (uid != 0).then(|| {
+                (
+                    format!("{} {} 1", uid, uid).into_bytes(),
+                    format!("{} {} 1", gid, gid).into_bytes(),
+                )
+            }),
+        }
+    }
+
+    fn has(&self, namespace: Namespace) -> bool {
+        self.namespaces.contains(&namespace)
+    }
+
+    /// Move the process into the namespaces
+    ///
+    /// This runs between fork and exec, so it may only make system calls. A new PID namespace
+    /// only applies to children, so the process forks once more and stays behind to pass on
+    /// how the command, the first process of the namespace, exited.
+    pub fn enter(&self) -> Result<()> {
+        let mut flags = 0;
+        for namespace in &self.namespaces {
+            flags |= match namespace {
+                Namespace::Net => libc::CLONE_NEWNET,
+                Namespace::Pid => libc::CLONE_NEWPID,
+                Namespace::Mount => libc::CLONE_NEWNS,
+            };
+        }
+        if self.id_maps.is_some() {
+            flags |= libc::CLONE_NEWUSER;
+        }
+        check(unsafe { libc::unshare(flags) })?;
+        if let Some((uid_map, gid_map)) = &self.id_maps {
+            write(c"/proc/self/setgroups", b"deny")?;
+            write(c"/proc/self/uid_map", uid_map)?;
+            write(c"/proc/self/gid_map", gid_map)?;
+        }
+        if self.has(Namespace::Mount) {
+            let flags = libc::MS_REC | libc::MS_PRIVATE;
+            check(unsafe {
+                libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    flags,
+                    std::ptr::null(),
+                )
+            })?;
+        }
+        if self.has(Namespace::Net) {
+            loopback_up()?;
+        }
+        if self.has(Namespace::Pid) {
+            let pid = check(unsafe { libc::fork() })?;
+            if pid > 0 {
+                wait_and_exit(pid);
+            }
+            if self.has(Namespace::Mount) {
+                // so tools like ps only see the processes of the namespace
+                check(unsafe {
+                    libc::mount(
+                        c"proc".as_ptr(),
+                        c"/proc".as_ptr(),
+                        c"proc".as_ptr(),
+                        0,
+                        std::ptr::null(),
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `struct ifreq` for reading and setting the flags of an interface
+#[repr(C)]
+struct InterfaceRequest {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    flags: libc::c_short,
+    /// The rest of the union of the request
+    _padding: [u8; 22],
+}
+
+/// Bring up the loopback interface, which starts out down in a new network namespace
+fn loopback_up() -> Result<()> {
+    let socket = check(unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) })?;
+    let mut request = unsafe { mem::zeroed::<InterfaceRequest>() };
+    for (to, from) in request.name.iter_mut().zip(b"lo") {
+        *to = *from as libc::c_char;
+    }
+    let result = unsafe {
+        libc::ioctl(socket, libc::SIOCGIFFLAGS, &mut request) >= 0 && {
+            request.flags |= libc::IFF_UP as libc::c_short;
+            libc::ioctl(socket, libc::SIOCSIFFLAGS, &request) >= 0
+        }
+    };
+    let err = io::Error::last_os_error();
+    unsafe { libc::close(socket) };
+    if !result {
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Wait for the command in the PID namespace and exit like it did, in the process hot started
+fn wait_and_exit(pid: libc::pid_t) -> ! {
+    unsafe {
+        // signals for the command reach it through its process group, this process only waits
+        let mut all = mem::zeroed::<libc::sigset_t>();
+        libc::sigfillset(&mut all);
+        libc::sigprocmask(libc::SIG_BLOCK, &all, std::ptr::null_mut());
+        // hot only learns that the command started once the descriptors it watches for exec
+        // are closed
+        if libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) < 0 {
+            for fd in 3..1024 {
+                libc::close(fd);
+            }
+        }
+        let mut status = 0;
+        while libc::waitpid(pid, &mut status, 0) < 0 {
+            if *libc::__errno_location() != libc::EINTR {
+                libc::_exit(1);
+            }
+        }
+        if libc::WIFSIGNALED(status) {
+            let signal = libc::WTERMSIG(status);
+            libc::signal(signal, libc::SIG_DFL);
+            libc::sigprocmask(libc::SIG_UNBLOCK, &all, std::ptr::null_mut());
+            libc::raise(signal);
+            libc::_exit(128 + signal);
+        }
+        libc::_exit(libc::WEXITSTATUS(status))
+    }
+}
@@ -0,0 +1,630 @@
+//! The stdout and stderr of hot, which the output of the command is copied to as far as they
+//! take it, so a slow or non-blocking terminal neither holds up hot nor loses output
+
+use crate::{screen, scrollback, Stream};
+use mio::{unix::SourceFd, Interest, Registry, Token};
+use std::{
+    io::{self, ErrorKind, Result, Write},
+    mem,
+    os::fd::RawFd,
+    ptr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// Output not written yet, for stdout and for stderr, kept in order behind anything written
+/// later
+static PENDING: Mutex<[Vec<u8>; 2]> = Mutex::new([Vec::new(), Vec::new()]);
+
+/// The flags of stdout and stderr before `unblock`, which they get back while hot lends them
+static FLAGS: OnceLock<[libc::c_int; 2]> = OnceLock::new();
+/// Whether hot writes to stdout and stderr without blocking
+static UNBLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Streams output can't be spliced to, like terminals
+static UNSPLICEABLE: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
+/// The most output moved by one splice, the default size of a pipe
+const SPLICE_LENGTH: usize = 64 * 1024;
+
+/// Whether a stream of hot is a terminal, which output is only styled for
+pub fn is_terminal(stream: Stream) -> bool {
+    static TERMINALS: OnceLock<[bool; 2]> = OnceLock::new();
+    let terminals = TERMINALS.get_or_init(|| {
+        [libc::STDOUT_FILENO, libc::STDERR_FILENO].map(|fd| unsafe { libc::isatty(fd) } == 1)
+    });
+    terminals[slot(stream).0]
+}
+
+fn slot(stream: Stream) -> (usize, RawFd) {
+    match stream {
+        Stream::Stdout => (0, libc::STDOUT_FILENO),
+        Stream::Stderr => (1, libc::STDERR_FILENO),
+    }
+}
+
+/// Set the flags of stdout and stderr, which a function of their flags before `unblock` picks
+fn set_flags(flags: impl Fn(libc::c_int) -> libc::c_int) {
+    let saved = FLAGS.get_or_init(|| {
+        [libc::STDOUT_FILENO, libc::STDERR_FILENO]
+            .map(|fd| unsafe { libc::fcntl(fd, libc::F_GETFL) })
+    });
+    for (fd, &saved) in [libc::STDOUT_FILENO, libc::STDERR_FILENO].iter().zip(saved) {
+        if saved >= 0 {
+            unsafe { libc::fcntl(*fd, libc::F_SETFL, flags(saved)) };
+        }
+    }
+}
+
+/// Stop writes to stdout and stderr from blocking, so a slow reader of hot's output holds up
+/// neither hot nor the output of the command, which waits in `PENDING` instead
+///
+/// The flags are shared with whatever else writes to the same terminal, pipe or file, so they
+/// are given back when hot exits and while `lend` lends the streams out.
+pub fn unblock() {
+    UNBLOCKED.store(true, Ordering::Relaxed);
+    screen::restore_at_exit();
+    set_flags(|flags| flags | libc::O_NONBLOCK);
+}
+
+/// Give stdout and stderr back the flags they had for something else writing to them, like the
+/// shell while hot is suspended, until `reclaim`
+pub fn lend() {
+    if FLAGS.get().is_some() {
+        set_flags(|flags| flags);
+    }
+}
+
+/// Stop writes to stdout and stderr from blocking again after `lend`, if they didn't before
+pub fn reclaim() {
+    if UNBLOCKED.load(Ordering::Relaxed) {
+        set_flags(|flags| flags | libc::O_NONBLOCK);
+    }
+}
+
+/// Write as much as the descriptor takes without blocking, returning how much that was
+fn write_some(fd: RawFd, bytes: &[u8]) -> Result<usize> {
+    let mut written = 0;
+    while written < bytes.len() {
+        let rest = &bytes[written..];
+        let result = unsafe { libc::write(fd, rest.as_ptr().cast(), rest.len()) };
+        if result >= 0 {
+            written += result as usize;
+            continue;
+        }
+        let err = io::Error::last_os_error();
+        match err.kind() {
+            ErrorKind::Interrupted => {}
+            ErrorKind::WouldBlock => break,
+            _ => return Err(err),
+        }
+    }
+    Ok(written)
+}
+
+/// Write to a stream of hot, keeping what it doesn't take yet for `flush`
+pub fn write(stream: Stream, bytes: &[u8]) -> Result<()> {
+    let (index, fd) = slot(stream);
+    let mut pending = PENDING.lock().unwrap();
+    let buffer = &mut pending[index];
+    let written = if buffer.is_empty() {
+        write_some(fd, bytes)?
+    } else {
+        0
+    };
+    if written < bytes.len() {
+        buffer.extend_from_slice(&bytes[written..]);
+        static AT_EXIT: Once = Once::new();
+        AT_EXIT.call_once(|| unsafe {
+            libc::atexit(finish);
+        });
+    }
+    Ok(())
+}
+
+/// Write a line of hot's own to stderr, dropping it once stderr is gone, or hold it back while
+/// the scrollback view or an overlay is drawn there
+pub fn message(line: &str) {
+    let bytes = format!("{}\n", line).into_bytes();
+    if let Some(bytes) = scrollback::hold_message(Stream::Stderr, bytes) {
+        let _ = write(Stream::Stderr, &bytes);
+    }
+}
+
+/// Move what is waiting in a pipe of the command to a stream of hot within the kernel,
+/// returning how much that was, or nothing when it has to be copied instead
+///
+/// Only as much as the pipe holds is moved, so the splice doesn't wait for more, and only while
+/// no output is kept for the stream, so it stays in order.
+pub fn splice(stream: Stream, from: RawFd) -> Result<Option<usize>> {
+    let (index, fd) = slot(stream);
+    if UNSPLICEABLE[index].load(Ordering::Relaxed) || !PENDING.lock().unwrap()[index].is_empty() {
+        return Ok(None);
+    }
+    let mut waiting: libc::c_int = 0;
+    if unsafe { libc::ioctl(from, libc::FIONREAD, &mut waiting) } < 0 {
+        return Ok(None);
+    }
+    if waiting <= 0 {
+        return Ok(Some(0));
+    }
+    let length = (waiting as usize).min(SPLICE_LENGTH);
+    loop {
+        let moved = unsafe {
+            libc::splice(
+                from,
+                ptr::null_mut(),
+                fd,
+                ptr::null_mut(),
+                length,
+                libc::SPLICE_F_MOVE,
+            )
+        };
+        if moved >= 0 {
+            return Ok(Some(moved as usize));
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => {}
+            // a non-blocking stream which doesn't take more now, copying keeps the rest
+            Some(libc::EAGAIN) => return Ok(None),
+            Some(libc::EINVAL) => {
+                UNSPLICEABLE[index].store(true, Ordering::Relaxed);
+                return Ok(None);
+            }
+            _ => return Err(err),
+        }
+    }
+}
+
+/// Write as much of the kept output as the streams take now
+pub fn flush() -> Result<()> {
+    let mut pending = PENDING.lock().unwrap();
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        let (index, fd) = slot(stream);
+        let buffer = &mut pending[index];
+        if !buffer.is_empty() {
+            let written = write_some(fd, buffer)?;
+            buffer.drain(..written);
+        }
+    }
+    Ok(())
+}
+
+fn is_pending(stream: Stream) -> bool {
+    !PENDING.lock().unwrap()[slot(stream).0].is_empty()
+}
+
+/// How much output is kept for both streams
+pub fn pending() -> usize {
+    PENDING.lock().unwrap().iter().map(Vec::len).sum()
+}
+
+/// Write the rest of the kept output before hot exits, waiting for the streams to take it
+extern "C" fn finish() {
+    let Ok(mut pending) = PENDING.try_lock() else {
+        return;
+    };
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        let (index, fd) = slot(stream);
+        let buffer = &mut pending[index];
+        while !buffer.is_empty() {
+            let Ok(written) = write_some(fd, buffer) else {
+                break;
+            };
+            buffer.drain(..written);
+            let mut ready = libc::pollfd {
+                fd,
+                events: libc::POLLOUT,
+                revents: 0,
+            };
+            if !buffer.is_empty() && unsafe { libc::poll(&mut ready, 1, -1) } < 0 {
+                break;
+            }
+        }
+    }
+}
+
+/// The encoding output of the command is decoded from, into UTF-8
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 with invalid bytes replaced by `�`
+    Utf8,
+    /// ISO-8859-1
+    Latin1,
+    Windows1252,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            _ => Err(format!("unknown encoding '{}'", s)),
+        }
+    }
+}
+
+/// The characters of Windows-1252 from `0x80` to `0x9f`, where it differs from ISO-8859-1
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// How output of the command which looks binary is shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binary {
+    /// As it is, like text
+    Show,
+    Suppress,
+    /// As a hex dump
+    Hex,
+}
+
+impl FromStr for Binary {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "show" => Ok(Binary::Show),
+            "suppress" => Ok(Binary::Suppress),
+            "hex" => Ok(Binary::Hex),
+            _ => Err(format!("unknown binary mode '{}'", s)),
+        }
+    }
+}
+
+/// Whether some output looks binary rather than like text, with a NUL byte or more than a
+/// tenth of it control characters text doesn't have
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(1024)];
+    let controls = sample
+        .iter()
+        .filter(|&&byte| match byte {
+            b'\t' | b'\n' | b'\r' | 0x07 | 0x08 | 0x0c | 0x1b => false,
+            byte => byte < 0x20 || byte == 0x7f,
+        })
+        .count();
+    sample.contains(&0) || controls * 10 > sample.len()
+}
+
+/// Where in an escape sequence the output of a stream is
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    None,
+    /// After `ESC`
+    Start,
+    /// After `ESC` and intermediate bytes, before the final one
+    Intermediate,
+    /// A control sequence like colors, `ESC [` up to a final byte
+    Control,
+    /// A string like a window title, `ESC ]`, `ESC P` and the like up to `ESC \` or `BEL`
+    String,
+    /// After `ESC` in a string, which ends it with `\`
+    StringEnd,
+}
+
+impl Escape {
+    /// The state after a byte, and whether the byte is part of the sequence
+    fn next(self, byte: u8) -> (Self, bool) {
+        match (self, byte) {
+            (Self::None, 0x1b) => (Self::Start, true),
+            (Self::None, _) => (Self::None, false),
+            (Self::Start, b'[') => (Self::Control, true),
+            (Self::Start, b']' | b'P' | b'X' | b'^' | b'_') => (Self::String, true),
+            (Self::Start | Self::Intermediate, 0x20..=0x2f) => (Self::Intermediate, true),
+            (Self::Start | Self::Intermediate, _) => (Self::None, true),
+            (Self::Control, 0x1b) => (Self::Start, true),
+            // terminals carry out control characters like newlines within the sequence
+            (Self::Control, 0x00..=0x1f) => (Self::Control, false),
+            (Self::Control, 0x40..=0x7e) => (Self::None, true),
+            (Self::Control, _) => (Self::Control, true),
+            (Self::String, 0x07) => (Self::None, true),
+            (Self::String, 0x1b) => (Self::StringEnd, true),
+            (Self::String, _) => (Self::String, true),
+            (Self::StringEnd, b'\\') => (Self::None, true),
+            // an escape sequence cutting the string short
+            (Self::StringEnd, _) => Self::Start.next(byte),
+        }
+    }
+}
+
+/// The text of a line of output as it shows on its own, without escape sequences and control
+/// characters
+pub fn visible(line: &[u8]) -> String {
+    let mut escape = Escape::None;
+    let mut text = Vec::with_capacity(line.len());
+    for &byte in line {
+        let (next, escaped) = escape.next(byte);
+        escape = next;
+        match byte {
+            _ if escaped => {}
+            b'\t' => text.push(b' '),
+            byte if byte.is_ascii_control() => {}
+            byte => text.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+/// Where the lines of the output of one run of the command start and end
+///
+/// With `line-buffered`, output is held back until the line it is part of is complete, so
+/// lines of stdout and stderr, and of commands running at once, don't end up mixed.
+#[derive(Default)]
+pub struct Lines {
+    partial: [Vec<u8>; 2],
+    /// When the partial line of a stream started
+    since: [Option<Instant>; 2],
+    /// Whether a line of a stream started and didn't end yet
+    midline: [bool; 2],
+    /// Whether a stream wrote output which looks binary yet
+    binary: [bool; 2],
+    /// How much output of a stream was shown as a hex dump, where the next line of it starts
+    dumped: [u64; 2],
+    /// The start of a UTF-8 character the output of a stream ended in
+    undecoded: [Vec<u8>; 2],
+    /// Where in an escape sequence a stream is, as one may be split across reads
+    escapes: [Escape; 2],
+    /// Whether the output of a stream ended in a carriage return, left out if a newline follows
+    carriage: [bool; 2],
+    /// When the second lines are counted in for `max-lines-per-sec` started
+    second: Option<Instant>,
+    /// Lines shown within the second
+    shown: u32,
+    /// Lines suppressed within the second
+    suppressed: u64,
+    /// Whether the line a stream is in the middle of is shown, or nothing at the start of one
+    showing: [Option<bool>; 2],
+}
+
+impl Lines {
+    /// How long a partial line like a prompt is held back at most
+    pub const DELAY: Duration = Duration::from_millis(100);
+    /// How long a line may get before it is written anyway
+    const MAX_LINE: usize = 64 * 1024;
+
+    /// Copy some output of a stream to `to`, with `start` adding to it where each line starts
+    pub fn decorate<F>(&mut self, stream: Stream, bytes: &[u8], to: &mut Vec<u8>, mut start: F)
+    where
+        F: FnMut(&mut Vec<u8>),
+    {
+        let midline = &mut self.midline[slot(stream).0];
+        for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+            if !*midline {
+                start(to);
+            }
+            to.extend_from_slice(line);
+            *midline = !line.ends_with(b"\n");
+        }
+    }
+
+    /// Note that a stream wrote output which looks binary, returning whether it is the first
+    pub fn binary(&mut self, stream: Stream) -> bool {
+        !mem::replace(&mut self.binary[slot(stream).0], true)
+    }
+
+    /// Copy some output of a stream to `to` as a hex dump, like `hexdump -C` writes it
+    pub fn hexdump(&mut self, stream: Stream, bytes: &[u8], to: &mut Vec<u8>) {
+        let dumped = &mut self.dumped[slot(stream).0];
+        for chunk in bytes.chunks(16) {
+            let _ = write!(to, "{:08x} ", dumped);
+            for index in 0..16 {
+                if index == 8 {
+                    to.push(b' ');
+                }
+                let _ = match chunk.get(index) {
+                    Some(byte) => write!(to, " {:02x}", byte),
+                    None => write!(to, "   "),
+                };
+            }
+            to.extend_from_slice(b"  |");
+            to.extend(chunk.iter().map(|&byte| match byte {
+                0x20..=0x7e => byte,
+                _ => b'.',
+            }));
+            to.extend_from_slice(b"|\n");
+            *dumped += chunk.len() as u64;
+        }
+    }
+
+    /// Copy some output of a stream to `to` as UTF-8, decoding it from `encoding`
+    pub fn decode(&mut self, stream: Stream, bytes: &[u8], encoding: Encoding, to: &mut Vec<u8>) {
+        let mut buffer = [0; 4];
+        let mut push = |c: char| to.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+        match encoding {
+            Encoding::Latin1 => bytes.iter().for_each(|&byte| push(byte as char)),
+            Encoding::Windows1252 => bytes.iter().for_each(|&byte| match byte {
+                0x80..=0x9f => push(WINDOWS_1252[byte as usize - 0x80]),
+                _ => push(byte as char),
+            }),
+            Encoding::Utf8 => {
+                let undecoded = &mut self.undecoded[slot(stream).0];
+                let joined;
+                let mut rest = if undecoded.is_empty() {
+                    bytes
+                } else {
+                    undecoded.extend_from_slice(bytes);
+                    joined = mem::take(undecoded);
+                    &joined
+                };
+                while !rest.is_empty() {
+                    match std::str::from_utf8(rest) {
+                        Ok(valid) => {
+                            to.extend_from_slice(valid.as_bytes());
+                            break;
+                        }
+                        Err(err) => {
+                            let (valid, invalid) = rest.split_at(err.valid_up_to());
+                            to.extend_from_slice(valid);
+                            let Some(length) = err.error_len() else {
+                                // a character cut short by the end of the read
+                                undecoded.extend_from_slice(invalid);
+                                break;
+                            };
+                            to.extend_from_slice("\u{fffd}".as_bytes());
+                            rest = &invalid[length..];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copy some output of a stream to `to` without its ANSI escape sequences, like colors and
+    /// cursor movements
+    pub fn strip(&mut self, stream: Stream, bytes: &[u8], to: &mut Vec<u8>) {
+        let escape = &mut self.escapes[slot(stream).0];
+        for &byte in bytes {
+            let (next, escaped) = escape.next(byte);
+            *escape = next;
+            if !escaped {
+                to.push(byte);
+            }
+        }
+    }
+
+    /// Copy some output of a stream to `to` with its `\r\n` line endings turned into `\n`
+    pub fn normalize(&mut self, stream: Stream, bytes: &[u8], to: &mut Vec<u8>) {
+        let carriage = &mut self.carriage[slot(stream).0];
+        for &byte in bytes {
+            if mem::take(carriage) && byte != b'\n' {
+                to.push(b'\r');
+            }
+            if byte == b'\r' {
+                *carriage = true;
+            } else {
+                to.push(byte);
+            }
+        }
+    }
+
+    /// Copy the lines of some output of a stream to `to` while fewer than `max` lines were
+    /// shown within the second, counting the others as suppressed
+    pub fn limit(&mut self, stream: Stream, bytes: &[u8], max: u32, to: &mut Vec<u8>) {
+        let index = slot(stream).0;
+        for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+            let showing = match self.showing[index] {
+                Some(showing) => showing,
+                None => {
+                    self.second.get_or_insert_with(Instant::now);
+                    if self.shown < max {
+                        self.shown += 1;
+                        true
+                    } else {
+                        self.suppressed += 1;
+                        false
+                    }
+                }
+            };
+            if showing {
+                to.extend_from_slice(line);
+            }
+            self.showing[index] = (!line.ends_with(b"\n")).then_some(showing);
+        }
+    }
+
+    /// How many lines were suppressed within a second which is over, if any, starting to count
+    /// again
+    pub fn suppressed(&mut self) -> Option<u64> {
+        if self
+            .second
+            .is_none_or(|second| second.elapsed() < Duration::from_secs(1))
+        {
+            return None;
+        }
+        self.second = None;
+        self.shown = 0;
+        Some(mem::take(&mut self.suppressed)).filter(|&suppressed| suppressed > 0)
+    }
+
+    /// Pass the complete lines of some output of a stream to `write`, holding back the rest
+    pub fn complete<F>(&mut self, stream: Stream, bytes: &[u8], mut write: F) -> Result<()>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        let index = slot(stream).0;
+        let partial = &mut self.partial[index];
+        let end = bytes
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let (complete, rest) = bytes.split_at(end);
+        if !complete.is_empty() {
+            if partial.is_empty() {
+                write(complete)?;
+            } else {
+                partial.extend_from_slice(complete);
+                write(partial)?;
+                partial.clear();
+                self.since[index] = None;
+            }
+        }
+        if !rest.is_empty() {
+            partial.extend_from_slice(rest);
+            self.since[index].get_or_insert_with(Instant::now);
+        }
+        if partial.len() > Self::MAX_LINE {
+            return self.flush(stream, Duration::ZERO, write);
+        }
+        Ok(())
+    }
+
+    /// Pass a partial line held back for longer than `after` to `write`
+    pub fn flush<F>(&mut self, stream: Stream, after: Duration, mut write: F) -> Result<()>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        let index = slot(stream).0;
+        if self.since[index].is_some_and(|since| since.elapsed() >= after) {
+            write(&self.partial[index])?;
+            self.partial[index].clear();
+            self.since[index] = None;
+        }
+        Ok(())
+    }
+}
+
+/// The streams of hot registered to wake up the poll once they take more, while output is
+/// kept for them
+#[derive(Default)]
+pub struct Waiting {
+    registered: [bool; 2],
+}
+
+impl Waiting {
+    pub const STDOUT: Token = Token(3);
+    pub const STDERR: Token = Token(4);
+    /// How soon the kept output is tried again for a stream which can't be polled
+    pub const RETRY: Duration = Duration::from_millis(10);
+
+    /// Register the streams output is kept for and deregister the others, returning whether
+    /// output is kept for a stream which can't be polled, like a regular file
+    pub fn update(&mut self, registry: &Registry) -> Result<bool> {
+        let mut unpolled = false;
+        for (stream, token) in [
+            (Stream::Stdout, Self::STDOUT),
+            (Stream::Stderr, Self::STDERR),
+        ] {
+            let (index, fd) = slot(stream);
+            let pending = is_pending(stream);
+            let registered = &mut self.registered[index];
+            if pending && !*registered {
+                match registry.register(&mut SourceFd(&fd), token, Interest::WRITABLE) {
+                    Ok(()) => *registered = true,
+                    Err(_) => unpolled = true,
+                }
+            } else if !pending && *registered {
+                registry.deregister(&mut SourceFd(&fd))?;
+                *registered = false;
+            }
+        }
+        Ok(unpolled)
+    }
+}
@@ -0,0 +1,52 @@
+//! A file recording the PIDs of hot and the command, so a later hot can stop a command left
+//! running by one which crashed
+//!
+//! The first line is the PID of hot and each following line the process group of a command.
+
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    process,
+};
+
+fn context(path: &Path, err: Error) -> Error {
+    Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
+
+/// The PID of the hot which wrote the file and the process groups of its commands, if there
+/// is a file
+pub fn read(path: &Path) -> Result<Option<(u32, Vec<u32>)>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(context(path, err)),
+    };
+    let mut pids = text.lines().map(|line| {
+        line.trim().parse::<u32>().map_err(|_| {
+            let message = format!("{}: expected a PID, found '{}'", path.display(), line);
+            Error::new(ErrorKind::InvalidData, message)
+        })
+    });
+    let Some(hot) = pids.next().transpose()? else {
+        return Ok(None);
+    };
+    Ok(Some((hot, pids.collect::<Result<_>>()?)))
+}
+
+/// Record the PID of this hot and the process groups of the commands it runs
+pub fn write(path: &Path, groups: &[u32]) -> Result<()> {
+    let mut text = format!("{}\n", process::id());
+    for group in groups {
+        text.push_str(&format!("{}\n", group));
+    }
+    fs::write(path, text).map_err(|err| context(path, err))
+}
+
+/// Remove the file once hot exits cleanly
+pub fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(context(path, err)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,101 @@
+//! CPU and I/O scheduling priorities of the command, so it doesn't starve the rest of the machine
+
+use std::{
+    io::{self, Result},
+    str::FromStr,
+};
+
+/// `ioprio_set` applies to a single process
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+/// The class of an I/O priority is stored above its level
+const IOPRIO_CLASS_SHIFT: u32 = 13;
+
+/// A niceness from -20, the highest priority, to 19, the lowest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Nice(i32);
+
+impl Nice {
+    /// Set the niceness of the process
+    ///
+    /// This runs between fork and exec, so it may only make system calls.
+    pub fn apply(self) -> Result<()> {
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, self.0) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Nice {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().parse() {
+            Ok(nice @ -20..=19) => Ok(Nice(nice)),
+            _ => Err(format!("invalid niceness '{}', expected -20 to 19", s)),
+        }
+    }
+}
+
+/// The class of an I/O priority, as used by `ionice`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Realtime = 1,
+    BestEffort = 2,
+    Idle = 3,
+}
+
+/// An I/O priority, a class with a level from 0, the highest, to 7 for the scheduled ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoNice {
+    class: Class,
+    level: u32,
+}
+
+impl IoNice {
+    /// Set the I/O priority of the process
+    ///
+    /// This runs between fork and exec, so it may only make system calls.
+    pub fn apply(self) -> Result<()> {
+        let priority = (self.class as u32) << IOPRIO_CLASS_SHIFT | self.level;
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                priority as libc::c_int,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// Accepts `idle`, `CLASS:LEVEL` with `realtime` or `best-effort`, or just a level of the
+/// best-effort class
+impl FromStr for IoNice {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (class, level) = match s.trim().split_once(':') {
+            Some((class, level)) => (class, Some(level)),
+            None if s.trim().starts_with(|c: char| c.is_ascii_digit()) => ("best-effort", Some(s)),
+            None => (s.trim(), None),
+        };
+        let class = match class {
+            "realtime" | "rt" => Class::Realtime,
+            "best-effort" | "be" => Class::BestEffort,
+            "idle" => Class::Idle,
+            _ => return Err(format!("unknown I/O scheduling class '{}'", class)),
+        };
+        let level = match (class, level) {
+            (Class::Idle, Some(_)) => return Err("the idle class has no levels".into()),
+            (_, None) => 4,
+            (_, Some(level)) => match level.trim().parse() {
+                Ok(level @ 0..=7) => level,
+                _ => return Err(format!("invalid I/O priority '{}', expected 0 to 7", level)),
+            },
+        };
+        Ok(IoNice { class, level })
+    }
+}
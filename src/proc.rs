@@ -0,0 +1,50 @@
+//! The state of processes as Linux reports it in `/proc`
+
+use std::fs;
+
+/// What is used by the processes of a process group
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+    /// Resident memory in bytes
+    pub memory: u64,
+    /// Time spent on the CPU in clock ticks
+    pub cpu_ticks: u64,
+}
+
+/// Clock ticks CPU time is counted in
+pub fn ticks_per_second() -> f64 {
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK) as f64 }
+}
+
+/// Add up the usage of every process in a process group
+pub fn group_usage(pgid: u32) -> Usage {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let mut usage = Usage::default();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return usage;
+    };
+    for entry in entries.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(|c: char| c.is_ascii_digit())
+        {
+            continue;
+        }
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        // the fields after the command name, which is in parentheses and may contain spaces
+        let Some((_, fields)) = stat.rsplit_once(')') else {
+            continue;
+        };
+        let fields = fields.split_whitespace().collect::<Vec<_>>();
+        let field = |i: usize| fields.get(i).and_then(|field| field.parse::<u64>().ok());
+        if field(2) != Some(pgid.into()) {
+            continue;
+        }
+        usage.memory += field(21).unwrap_or(0) * page_size;
+        usage.cpu_ticks += field(11).unwrap_or(0) + field(12).unwrap_or(0);
+    }
+    usage
+}
@@ -0,0 +1,132 @@
+//! Pseudo-terminals, so the command writes colors and whole lines as it does to a terminal
+
+use std::{
+    ffi::CStr,
+    io::{self, Result},
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::OnceLock,
+};
+
+/// The mode of the terminal hot runs in before `keep_input`, restored when hot exits
+static SAVED_MODE: OnceLock<libc::termios> = OnceLock::new();
+
+/// The size used when hot itself doesn't run in a terminal
+pub const DEFAULT_SIZE: libc::winsize = libc::winsize {
+    ws_row: 24,
+    ws_col: 80,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+};
+
+/// A pseudo-terminal, of which the command gets the slave side and hot reads the master side
+pub struct Pty {
+    pub master: OwnedFd,
+    pub slave: OwnedFd,
+}
+
+fn check(result: libc::c_int) -> Result<libc::c_int> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(result)
+}
+
+impl Pty {
+    /// Open a pseudo-terminal the size of the terminal hot runs in
+    pub fn open() -> Result<Self> {
+        let flags = libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC;
+        let master = unsafe { OwnedFd::from_raw_fd(check(libc::posix_openpt(flags))?) };
+        let fd = master.as_raw_fd();
+        check(unsafe { libc::grantpt(fd) })?;
+        check(unsafe { libc::unlockpt(fd) })?;
+        let mut name = [0 as libc::c_char; 128];
+        if unsafe { libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) };
+        let slave = unsafe { check(libc::open(path.as_ptr(), flags))? };
+        let pty = Self {
+            master,
+            slave: unsafe { OwnedFd::from_raw_fd(slave) },
+        };
+        resize(fd, window_size().unwrap_or(DEFAULT_SIZE))?;
+        Ok(pty)
+    }
+}
+
+/// The size of the terminal hot runs in, if it runs in one
+pub fn window_size() -> Option<libc::winsize> {
+    [libc::STDOUT_FILENO, libc::STDERR_FILENO, libc::STDIN_FILENO]
+        .into_iter()
+        .find_map(|fd| {
+            let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+            let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+            (result == 0 && size.ws_col > 0).then_some(size)
+        })
+}
+
+/// Set the size of a pseudo-terminal by its master side
+pub fn resize(master: RawFd, size: libc::winsize) -> Result<()> {
+    check(unsafe { libc::ioctl(master, libc::TIOCSWINSZ, &size) })?;
+    Ok(())
+}
+
+/// Start a session of its own for the command, with the terminal on its stdin as the
+/// controlling terminal
+///
+/// This runs between fork and exec, so it may only make system calls.
+pub fn make_controlling() -> Result<()> {
+    check(unsafe { libc::setsid() })?;
+    check(unsafe { libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0) })?;
+    Ok(())
+}
+
+/// Make the process group of the command the foreground of the terminal on its stdin, so it
+/// can read it, when it is one
+///
+/// This runs between fork and exec, so it may only make system calls.
+pub fn take_foreground() -> Result<()> {
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        return Ok(());
+    }
+    // only the foreground may change the foreground without being stopped for it
+    let previous = unsafe { libc::signal(libc::SIGTTOU, libc::SIG_IGN) };
+    let result = unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp()) };
+    unsafe { libc::signal(libc::SIGTTOU, previous) };
+    check(result)?;
+    Ok(())
+}
+
+/// Let hot read every key as it is typed, without the terminal echoing it or turning it into a
+/// signal, so it can be passed on to the command, until hot exits
+///
+/// Output is still processed, so lines written by the command start at the left edge.
+pub fn keep_input() -> Result<()> {
+    let mut mode = unsafe { mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut mode) } < 0 {
+        // not a terminal, there is nothing to type
+        return Ok(());
+    }
+    if SAVED_MODE.set(mode).is_ok() {
+        unsafe { libc::atexit(restore_mode) };
+    }
+    mode.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+    mode.c_iflag &= !(libc::IXON | libc::ICRNL);
+    check(unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode) })?;
+    Ok(())
+}
+
+/// Have the terminal echo keys and collect lines again as before `keep_input`
+pub fn release_input() -> Result<()> {
+    if let Some(mode) = SAVED_MODE.get() {
+        check(unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, mode) })?;
+    }
+    Ok(())
+}
+
+extern "C" fn restore_mode() {
+    if let Some(mode) = SAVED_MODE.get() {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, mode) };
+    }
+}
@@ -0,0 +1,191 @@
+//! Probes which tell when the command is ready, like a port accepting connections
+
+use crate::regex::Regex;
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// How often probes which connect to the command are tried
+const INTERVAL: Duration = Duration::from_millis(250);
+/// How long a probe may take to connect and to get a response
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+/// Most of a response read before closing the connection
+const MAX_RESPONSE: u64 = 1 << 20;
+/// Longest line of output kept for matching, the rest of a longer line is skipped
+const MAX_LINE: usize = 4096;
+
+pub enum Probe {
+    /// An address accepting TCP connections
+    Tcp(String),
+    /// An `http://` URL responding with a success or redirect status
+    Http(Url),
+    /// A line of output matching a pattern
+    Log(Regex),
+}
+
+impl Probe {
+    /// Try a probe which connects to the command
+    fn connects(&self) -> bool {
+        match self {
+            Probe::Tcp(address) => connect(address).is_some(),
+            Probe::Http(url) => url.responds(),
+            Probe::Log(_) => false,
+        }
+    }
+}
+
+fn connect(address: &str) -> Option<TcpStream> {
+    address
+        .to_socket_addrs()
+        .ok()?
+        .find_map(|address| TcpStream::connect_timeout(&address, PROBE_TIMEOUT).ok())
+}
+
+pub struct Url {
+    /// `host:port`, with port 80 when the URL has none
+    address: String,
+    host: String,
+    path: String,
+}
+
+impl Url {
+    fn responds(&self) -> bool {
+        let Some(mut stream) = connect(&self.address) else {
+            return false;
+        };
+        let request = format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.path, self.host
+        );
+        if stream.set_read_timeout(Some(PROBE_TIMEOUT)).is_err()
+            || stream.write_all(request.as_bytes()).is_err()
+        {
+            return false;
+        }
+        let mut response = [0; 32];
+        let read = stream.read(&mut response).unwrap_or(0);
+        // read the rest, as servers may complain about connections closed before that
+        let _ = io::copy(&mut stream.take(MAX_RESPONSE), &mut io::sink());
+        // a status line like `HTTP/1.1 200 OK`
+        let status = String::from_utf8_lossy(&response[..read]);
+        status
+            .split_whitespace()
+            .nth(1)
+            .is_some_and(|code| code.starts_with(['2', '3']))
+    }
+}
+
+impl FromStr for Url {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("expected an http:// URL, found '{}'", s))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(format!("missing host in URL '{}'", s));
+        }
+        let address = match authority.rsplit_once(':') {
+            Some((_, port)) if !port.contains(']') => authority.to_owned(),
+            _ => format!("{}:80", authority),
+        };
+        Ok(Self {
+            address,
+            host: authority.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// A service the command depends on, given as `tcp://host:port` or `host:port`
+pub struct Dependency {
+    source: String,
+    address: String,
+}
+
+impl Dependency {
+    /// Whether the service accepts connections
+    pub fn is_up(&self) -> bool {
+        connect(&self.address).is_some()
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let address = match s.split_once("://") {
+            Some(("tcp", address)) => address,
+            Some((scheme, _)) => return Err(format!("unsupported scheme '{}' in '{}'", scheme, s)),
+            None => s,
+        };
+        if !address.contains(':') {
+            return Err(format!("expected a host and port in '{}'", s));
+        }
+        Ok(Self {
+            source: s.to_owned(),
+            address: address.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// The probes one run of the command has yet to pass
+pub struct Readiness {
+    /// Indexes of the probes which didn't pass yet
+    pending: Vec<usize>,
+    line: Vec<u8>,
+    next_try: Instant,
+}
+
+impl Readiness {
+    pub fn new(probes: &[Probe]) -> Self {
+        Self {
+            pending: (0..probes.len()).collect(),
+            line: Vec::new(),
+            next_try: Instant::now(),
+        }
+    }
+
+    /// Match the lines of some output of the command against the log probes
+    pub fn output(&mut self, probes: &[Probe], bytes: &[u8]) {
+        for &byte in bytes {
+            if byte != b'\n' {
+                if self.line.len() < MAX_LINE {
+                    self.line.push(byte);
+                }
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line);
+            self.pending.retain(|&i| match probes.get(i) {
+                Some(Probe::Log(regex)) => !regex.is_match(line.trim_end_matches('\r')),
+                _ => true,
+            });
+            self.line.clear();
+        }
+    }
+
+    /// Try the probes which connect to the command when it is time to, returning whether
+    /// every probe passed
+    ///
+    /// Probes which were removed by reloading the configuration count as passed.
+    pub fn check(&mut self, probes: &[Probe]) -> bool {
+        if !self.pending.is_empty() && Instant::now() >= self.next_try {
+            self.pending
+                .retain(|&i| probes.get(i).is_some_and(|probe| !probe.connects()));
+            self.next_try = Instant::now() + INTERVAL;
+        }
+        self.pending.is_empty()
+    }
+}
@@ -0,0 +1,316 @@
+//! Regular expressions for matching lines of output
+//!
+//! Supports literals, `.`, `^` and `$`, classes like `[a-z]` or `[^0-9]`, `\d`, `\w`, `\s`
+//! and their negations, `\b`, groups with `|` alternatives and the repetitions `*`, `+`, `?`
+//! and `{n,m}`. Matching is by backtracking, which is plenty for lines of output.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    /// `.`, any character
+    Any,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `\b`, between a word character and something else
+    WordBoundary,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: usize,
+    },
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r')];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regex {
+    source: String,
+    alternatives: Vec<Vec<Node>>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{} in regex '{}'", message, self.source)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.i += 1;
+        c
+    }
+
+    /// Alternatives separated by `|`, up to the end of the group
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.i += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let node = self.atom()?;
+            nodes.push(self.repeat(node)?);
+        }
+        Ok(nodes)
+    }
+
+    fn repeat(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            Some('{') => match self.bounds() {
+                Some(bounds) => bounds,
+                None => return Ok(node),
+            },
+            _ => return Ok(node),
+        };
+        if matches!(node, Node::Start | Node::End | Node::WordBoundary) {
+            return Err(self.error("nothing to repeat"));
+        }
+        self.i += 1;
+        // lazy repetitions match the same lines as greedy ones
+        if self.peek() == Some('?') {
+            self.i += 1;
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}`, leaving the position at the closing brace. Anything else is
+    /// a literal `{`.
+    fn bounds(&mut self) -> Option<(usize, usize)> {
+        let rest = self.chars[self.i + 1..].iter().collect::<String>();
+        let end = rest.find('}')?;
+        let bounds = match rest[..end].split_once(',') {
+            Some((min, "")) => (min.parse().ok()?, usize::MAX),
+            Some((min, max)) => (min.parse().ok()?, max.parse().ok()?),
+            None => {
+                let n = rest[..end].parse().ok()?;
+                (n, n)
+            }
+        };
+        self.i += rest[..end].chars().count() + 1;
+        Some(bounds)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        Ok(match self.next().expect("the sequence isn't over") {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                if self.chars[self.i..].starts_with(&['?', ':']) {
+                    self.i += 2;
+                }
+                let alternatives = self.alternatives()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("unclosed '('"));
+                }
+                Node::Group(alternatives)
+            }
+            '[' => self.class()?,
+            '*' | '+' | '?' => return Err(self.error("nothing to repeat")),
+            '\\' => match self.next().ok_or_else(|| self.error("trailing '\\'"))? {
+                'b' => Node::WordBoundary,
+                c => match perl_class(c) {
+                    Some((negated, ranges)) => Node::Class {
+                        negated,
+                        ranges: ranges.to_vec(),
+                    },
+                    None => Node::Char(escaped(c)),
+                },
+            },
+            c => Node::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.i += 1;
+        }
+        let mut ranges = Vec::new();
+        // a ']' right after the opening bracket is part of the class
+        let mut first = true;
+        loop {
+            let c = match self.next() {
+                Some(']') if !first => break,
+                Some('\\') => {
+                    let c = self.next().ok_or_else(|| self.error("unclosed '['"))?;
+                    match perl_class(c) {
+                        Some((false, class)) => {
+                            ranges.extend_from_slice(class);
+                            first = false;
+                            continue;
+                        }
+                        Some((true, _)) => {
+                            return Err(self.error("negated classes can't be used in '[...]'"))
+                        }
+                        None => escaped(c),
+                    }
+                }
+                Some(c) => c,
+                None => return Err(self.error("unclosed '['")),
+            };
+            first = false;
+            match (self.peek(), self.chars.get(self.i + 1)) {
+                (Some('-'), Some(&end)) if end != ']' => {
+                    self.i += 2;
+                    ranges.push((c, end));
+                }
+                _ => ranges.push((c, c)),
+            }
+        }
+        Ok(Node::Class { negated, ranges })
+    }
+}
+
+/// The class of an escape like `\d`, and whether it is negated
+fn perl_class(c: char) -> Option<(bool, &'static [(char, char)])> {
+    match c {
+        'd' => Some((false, DIGIT)),
+        'D' => Some((true, DIGIT)),
+        'w' => Some((false, WORD)),
+        'W' => Some((true, WORD)),
+        's' => Some((false, SPACE)),
+        'S' => Some((true, SPACE)),
+        _ => None,
+    }
+}
+
+fn escaped(c: char) -> char {
+    match c {
+        't' => '\t',
+        'n' => '\n',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+fn in_class(ranges: &[(char, char)], c: char) -> bool {
+    ranges.iter().any(|&(start, end)| start <= c && c <= end)
+}
+
+fn is_word(text: &[char], i: usize) -> bool {
+    text.get(i).is_some_and(|&c| in_class(WORD, c))
+}
+
+/// Whether `node` matches at `i` with the rest of the pattern, in `then`, matching after it
+fn match_node(node: &Node, text: &[char], i: usize, then: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Char(c) => text.get(i) == Some(c) && then(i + 1),
+        Node::Any => i < text.len() && then(i + 1),
+        Node::Class { negated, ranges } => match text.get(i) {
+            Some(&c) => in_class(ranges, c) != *negated && then(i + 1),
+            None => false,
+        },
+        Node::Start => i == 0 && then(i),
+        Node::End => i == text.len() && then(i),
+        Node::WordBoundary => {
+            let before = i > 0 && is_word(text, i - 1);
+            before != is_word(text, i) && then(i)
+        }
+        Node::Group(alternatives) => alternatives
+            .iter()
+            .any(|nodes| match_sequence(nodes, text, i, then)),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, text, i, then),
+    }
+}
+
+fn match_sequence(
+    nodes: &[Node],
+    text: &[char],
+    i: usize,
+    then: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        Some((node, rest)) => {
+            match_node(node, text, i, &mut |j| match_sequence(rest, text, j, then))
+        }
+        None => then(i),
+    }
+}
+
+/// Match as many repetitions as possible, giving them up one at a time until the rest
+/// matches
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: usize,
+    text: &[char],
+    i: usize,
+    then: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let more = max > 0
+        && match_node(node, text, i, &mut |j| {
+            // a repetition which matched nothing would match nothing forever
+            (j > i || min > 0) && match_repeat(node, min.saturating_sub(1), max - 1, text, j, then)
+        });
+    more || (min == 0 && then(i))
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            source: pattern,
+            chars: pattern.chars().collect(),
+            i: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unmatched ')'"));
+        }
+        Ok(Self {
+            source: pattern.to_owned(),
+            alternatives,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = text.chars().collect::<Vec<_>>();
+        (0..=text.len()).any(|i| {
+            self.alternatives
+                .iter()
+                .any(|nodes| match_sequence(nodes, &text, i, &mut |_| true))
+        })
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
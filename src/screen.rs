@@ -0,0 +1,220 @@
+//! The screen of the terminal hot runs in
+
+use crate::{output, Stream};
+use std::{
+    io::Result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+/// A mode of the terminal hot switches on, and off again while it is suspended and when it
+/// exits
+struct Mode {
+    on: &'static [u8],
+    off: &'static [u8],
+    enabled: AtomicBool,
+}
+
+impl Mode {
+    const fn new(on: &'static [u8], off: &'static [u8]) -> Self {
+        Self {
+            on,
+            off,
+            enabled: AtomicBool::new(false),
+        }
+    }
+}
+
+/// The alternate screen, which leaves the screen and scrollback of the terminal as they were
+static ALTERNATE: Mode = Mode::new(b"\x1b[?1049h", b"\x1b[?1049l");
+/// Bracketed paste, so pasted text can be told apart from typed keys
+static PASTE: Mode = Mode::new(b"\x1b[?2004h", b"\x1b[?2004l");
+/// Reports of mouse buttons, the wheel among them, instead of the terminal scrolling itself
+static MOUSE: Mode = Mode::new(b"\x1b[?1000h\x1b[?1006h", b"\x1b[?1006l\x1b[?1000l");
+const MODES: [&Mode; 3] = [&ALTERNATE, &PASTE, &MOUSE];
+
+/// Whether hot set the title of the terminal, after saving the one it had
+static TITLED: AtomicBool = AtomicBool::new(false);
+
+const CLEAR: &[u8] = b"\x1b[H\x1b[2J\x1b[3J";
+const SAVE_TITLE: &[u8] = b"\x1b[22;0t";
+const RESTORE_TITLE: &[u8] = b"\x1b[23;0t";
+
+/// The stream of hot which is a terminal, stdout if both are
+pub fn terminal() -> Option<Stream> {
+    [Stream::Stdout, Stream::Stderr]
+        .into_iter()
+        .find(|&stream| output::is_terminal(stream))
+}
+
+/// Clear the screen and the scrollback of the terminal
+pub fn clear() -> Result<()> {
+    match terminal() {
+        Some(stream) => output::write(stream, CLEAR),
+        None => Ok(()),
+    }
+}
+
+/// Ring the bell of the terminal, or with `notify` show a desktop notification with a message
+/// where the terminal supports OSC 9
+pub fn bell(message: &str, notify: bool) -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    if notify {
+        let message = message
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect::<String>();
+        output::write(stream, format!("\x1b]9;{}\x07", message).as_bytes())
+    } else {
+        output::write(stream, b"\x07")
+    }
+}
+
+fn switch_on(mode: &Mode) -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    restore_at_exit();
+    mode.enabled.store(true, Ordering::Relaxed);
+    output::write(stream, mode.on)
+}
+
+/// Switch to the alternate screen, which leaves the screen and scrollback of the terminal as
+/// they were until hot switches back when it exits
+pub fn enter_alternate() -> Result<()> {
+    switch_on(&ALTERNATE)
+}
+
+/// Switch back from the alternate screen
+pub fn leave_alternate() -> Result<()> {
+    match terminal() {
+        Some(stream) if ALTERNATE.enabled.swap(false, Ordering::Relaxed) => {
+            output::write(stream, ALTERNATE.off)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether hot switched to the alternate screen
+pub fn is_alternate() -> bool {
+    ALTERNATE.enabled.load(Ordering::Relaxed)
+}
+
+/// Copy text to the clipboard with OSC 52, where the terminal supports it
+pub fn copy(text: &str) -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    let text = base64(text.as_bytes());
+    output::write(stream, format!("\x1b]52;c;{}\x07", text).as_bytes())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+            bits | (byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// Have the terminal report the mouse wheel, when hot reads keys from it
+pub fn enable_mouse() -> Result<()> {
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        return Ok(());
+    }
+    switch_on(&MOUSE)
+}
+
+/// Have the terminal mark pasted text, when hot reads keys from it
+pub fn enable_paste() -> Result<()> {
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        return Ok(());
+    }
+    switch_on(&PASTE)
+}
+
+/// Switch the modes hot switched on off, to give the terminal back as it was while hot is
+/// suspended
+pub fn release() -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    for mode in MODES {
+        if mode.enabled.load(Ordering::Relaxed) {
+            output::write(stream, mode.off)?;
+        }
+    }
+    Ok(())
+}
+
+/// Switch the modes hot switched on on again, once it continues
+pub fn resume() -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    for mode in MODES {
+        if mode.enabled.load(Ordering::Relaxed) {
+            output::write(stream, mode.on)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set the title of the terminal, which gets the title it had back when hot exits, where the
+/// terminal saves titles
+pub fn set_title(title: &str) -> Result<()> {
+    let Some(stream) = terminal() else {
+        return Ok(());
+    };
+    restore_at_exit();
+    if !TITLED.swap(true, Ordering::Relaxed) {
+        output::write(stream, SAVE_TITLE)?;
+    }
+    let title = title
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    output::write(stream, format!("\x1b]0;{}\x07", title).as_bytes())
+}
+
+/// Have `restore` run when hot exits
+pub fn restore_at_exit() {
+    static AT_EXIT: Once = Once::new();
+    AT_EXIT.call_once(|| unsafe {
+        libc::atexit(restore);
+    });
+}
+
+/// Switch the modes hot switched on off, restore the title and give stdout and stderr back their
+/// flags when hot exits, after the rest of its output
+extern "C" fn restore() {
+    output::lend();
+    let fd = match terminal() {
+        Some(Stream::Stdout) => libc::STDOUT_FILENO,
+        Some(Stream::Stderr) => libc::STDERR_FILENO,
+        None => return,
+    };
+    let mut bytes = Vec::new();
+    for mode in MODES {
+        if mode.enabled.swap(false, Ordering::Relaxed) {
+            bytes.extend_from_slice(mode.off);
+        }
+    }
+    if TITLED.swap(false, Ordering::Relaxed) {
+        bytes.extend_from_slice(RESTORE_TITLE);
+    }
+    unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+}
@@ -0,0 +1,698 @@
+//! The output of the command kept to scroll back through it
+//!
+//! It is shown in a view on the alternate screen, which holds back new output until it is closed
+//! again, as overlays such as the help do and as pausing does. It can be searched with the
+//! matches highlighted, replayed a run at a time, and tells where the last error in it is.
+
+use crate::{output, pty, screen, Stream};
+use std::{collections::VecDeque, io::Result, mem, sync::Mutex};
+
+/// How many lines of an error `last_error` gives at most
+const ERROR_LINES: usize = 200;
+
+static SCROLLBACK: Mutex<Scrollback> = Mutex::new(Scrollback {
+    enabled: false,
+    limit: 10_000,
+    lines: VecDeque::new(),
+    forgotten: 0,
+    runs: VecDeque::new(),
+    partial: Vec::new(),
+    view: None,
+    overlay: None,
+    alternate: false,
+    paused: false,
+    held: VecDeque::new(),
+    held_lines: 0,
+    dropped: 0,
+    search: None,
+});
+
+struct Scrollback {
+    /// Whether lines are kept, with `scrollback` or `mouse`
+    enabled: bool,
+    /// How many lines are kept at most, and held back
+    limit: usize,
+    lines: VecDeque<Vec<u8>>,
+    /// How many lines were forgotten beyond the limit
+    forgotten: usize,
+    /// Where the last two runs of the command or the build started, counting the lines
+    /// forgotten
+    runs: VecDeque<usize>,
+    /// The line being written, which didn't end yet
+    partial: Vec<u8>,
+    /// The first line shown while the view is open
+    view: Option<usize>,
+    /// The lines of an overlay shown over the output or the view, until a key is pressed, and
+    /// its status line
+    overlay: Option<(Vec<String>, &'static str)>,
+    /// Whether hot was on the alternate screen before the view or an overlay was opened
+    alternate: bool,
+    /// Whether output is held back until it is resumed
+    paused: bool,
+    /// The output written while the view or an overlay is open or output is paused, and
+    /// whether it is among the lines kept, as hot's own isn't
+    held: VecDeque<(Stream, Vec<u8>, bool)>,
+    /// How many lines are held back
+    held_lines: usize,
+    /// How many lines held back were forgotten beyond the limit
+    dropped: usize,
+    /// The search through the lines kept, while the view shows it
+    search: Option<Search>,
+}
+
+/// A search through the lines kept
+struct Search {
+    query: Query,
+    /// The line of the match shown
+    at: Option<usize>,
+    /// The query with its cursor while it is typed, shown instead of the status line
+    prompt: Option<String>,
+    /// Which way there is no match beyond the one shown, once one was looked for
+    none: Option<&'static str>,
+    /// The first line shown before the search, to go back to when it ends
+    before: Option<usize>,
+}
+
+/// What is searched for, ignoring case unless it has capitals
+struct Query {
+    chars: Vec<char>,
+    sensitive: bool,
+}
+
+impl Query {
+    fn new(query: &str) -> Self {
+        Self {
+            chars: query.chars().collect(),
+            sensitive: query.chars().any(char::is_uppercase),
+        }
+    }
+
+    fn fold(&self, c: char) -> char {
+        match self.sensitive {
+            true => c,
+            false => c.to_lowercase().next().unwrap_or(c),
+        }
+    }
+
+    /// Where the query starts in some text, without overlapping
+    fn find(&self, text: &[char]) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut start = 0;
+        while !self.chars.is_empty() && start + self.chars.len() <= text.len() {
+            let matches = self
+                .chars
+                .iter()
+                .zip(&text[start..])
+                .all(|(&a, &b)| self.fold(a) == self.fold(b));
+            if matches {
+                found.push(start);
+                start += self.chars.len();
+            } else {
+                start += 1;
+            }
+        }
+        found
+    }
+
+    /// Some text with the query in reverse video wherever it is found
+    fn highlight(&self, text: &[char]) -> String {
+        let mut highlighted = String::new();
+        let mut end = 0;
+        for start in self.find(text) {
+            highlighted.extend(&text[end..start]);
+            end = start + self.chars.len();
+            highlighted.push_str("\x1b[7m");
+            highlighted.extend(&text[start..end]);
+            highlighted.push_str("\x1b[0m");
+        }
+        highlighted.extend(&text[end..]);
+        highlighted
+    }
+}
+
+impl Scrollback {
+    /// The lines kept, with the partial one
+    fn lines(&self) -> Vec<&[u8]> {
+        let mut lines = self.lines.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        if !self.partial.is_empty() {
+            lines.push(&self.partial);
+        }
+        lines
+    }
+
+    /// The first line shown when the view is at the bottom, with a row left for the status line
+    fn bottom(&self) -> usize {
+        self.lines().len().saturating_sub(rows().saturating_sub(1))
+    }
+
+    /// Keep some output, forgetting the first lines beyond the limit
+    fn keep(&mut self, bytes: &[u8]) {
+        for piece in bytes.split_inclusive(|&byte| byte == b'\n') {
+            self.partial.extend_from_slice(piece);
+            if piece.ends_with(b"\n") {
+                let line = mem::take(&mut self.partial);
+                self.lines.push_back(line);
+                if self.lines.len() > self.limit {
+                    self.lines.pop_front();
+                    self.forgotten += 1;
+                    // so the view keeps showing the same lines
+                    if let Some(top) = &mut self.view {
+                        *top = top.saturating_sub(1);
+                    }
+                    if let Some(search) = &mut self.search {
+                        search.at = search.at.map(|at| at.saturating_sub(1));
+                        search.before = search.before.map(|top| top.saturating_sub(1));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the view or an overlay is open
+    fn shown(&self) -> bool {
+        self.view.is_some() || self.overlay.is_some()
+    }
+
+    /// Switch to the alternate screen for the view or an overlay, unless hot is on it already
+    fn open(&mut self) -> Result<()> {
+        if self.shown() {
+            return Ok(());
+        }
+        self.alternate = screen::is_alternate();
+        match self.alternate {
+            true => Ok(()),
+            false => screen::enter_alternate(),
+        }
+    }
+
+    /// Give the screen back to the output once the view or an overlay closed, unless the view
+    /// is still open below an overlay
+    fn close(&mut self, stream: Stream) -> Result<()> {
+        if self.shown() {
+            return self.redraw(stream);
+        }
+        if !self.alternate {
+            screen::leave_alternate()?;
+        } else if self.enabled && !self.paused {
+            // the lines drawn include the output of the command held back, but not hot's own
+            let held = mem::take(&mut self.held);
+            self.held_lines = 0;
+            self.dropped = 0;
+            self.draw(stream, None)?;
+            for (stream, bytes, _) in held.into_iter().filter(|&(.., kept)| !kept) {
+                output::write(stream, &bytes)?;
+            }
+            return Ok(());
+        } else {
+            output::write(stream, b"\x1b[H\x1b[2J")?;
+        }
+        self.release()
+    }
+
+    /// Write the output held back, unless it still is
+    fn release(&mut self) -> Result<()> {
+        if self.shown() || self.paused {
+            return Ok(());
+        }
+        if let Some(&(stream, ..)) = self.held.front().filter(|_| self.dropped > 0) {
+            let message = format!("… {} lines dropped while held back\n", self.dropped);
+            output::write(stream, message.as_bytes())?;
+        }
+        for (stream, bytes, _) in mem::take(&mut self.held) {
+            output::write(stream, &bytes)?;
+        }
+        self.held_lines = 0;
+        self.dropped = 0;
+        Ok(())
+    }
+
+    /// Hold back some output, which is among the lines kept or not, forgetting the first lines
+    /// held back beyond the limit
+    fn hold(&mut self, stream: Stream, bytes: Vec<u8>, kept: bool) {
+        self.held_lines += lines(&bytes);
+        self.held.push_back((stream, bytes, kept));
+        while self.held_lines > self.limit {
+            let excess = self.held_lines - self.limit;
+            let (_, bytes, _) = &mut self.held[0];
+            let count = lines(bytes);
+            let forgotten = if count <= excess {
+                self.held.pop_front();
+                count
+            } else {
+                let newlines = bytes.iter().enumerate().filter(|&(_, &byte)| byte == b'\n');
+                let end = newlines.map(|(end, _)| end).nth(excess - 1).unwrap();
+                bytes.drain(..=end);
+                excess
+            };
+            self.held_lines -= forgotten;
+            self.dropped += forgotten;
+        }
+    }
+
+    /// Draw the lines from `top` on over the whole screen with a status line below, or without
+    /// `top` the last lines to follow the output again
+    fn draw(&self, stream: Stream, top: Option<usize>) -> Result<()> {
+        let size = pty::window_size().unwrap_or(pty::DEFAULT_SIZE);
+        let (rows, columns) = (size.ws_row as usize, size.ws_col as usize);
+        let lines = self.lines();
+        let start = top.unwrap_or_else(|| self.bottom()).min(self.bottom());
+        let end = lines.len().min(start + rows.saturating_sub(1));
+        let mut screen = b"\x1b[H\x1b[2J".to_vec();
+        for (index, line) in (start..end).zip(&lines[start..end]) {
+            let line = output::visible(line)
+                .chars()
+                .take(columns)
+                .collect::<Vec<_>>();
+            let line = match &self.search {
+                Some(search) => search.query.highlight(&line),
+                None => line.into_iter().collect(),
+            };
+            screen.extend(line.bytes());
+            // the partial line goes on once the view is closed
+            if index + 1 < end || (top.is_none() && self.partial.is_empty()) {
+                screen.extend_from_slice(b"\r\n");
+            }
+        }
+        match &self.search {
+            _ if top.is_none() => {}
+            Some(Search {
+                prompt: Some(prompt),
+                ..
+            }) => screen.extend(format!("\x1b[{};1H/{}", rows, prompt).bytes()),
+            Some(search) => status_line(&mut screen, &self.search_status(search)),
+            None => {
+                let status = format!(
+                    "lines {}-{} of {}, scroll down or press end to follow",
+                    start + 1,
+                    end,
+                    lines.len()
+                );
+                status_line(&mut screen, &status);
+            }
+        }
+        output::write(stream, &screen)
+    }
+
+    /// The lines kept which the search matches
+    fn matching(&self) -> Vec<usize> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        let lines = self.lines();
+        (0..lines.len())
+            .filter(|&index| {
+                let line = output::visible(lines[index]).chars().collect::<Vec<_>>();
+                !search.query.find(&line).is_empty()
+            })
+            .collect()
+    }
+
+    /// Where the search is at, for the status line
+    fn search_status(&self, search: &Search) -> String {
+        let Some(at) = search.at else {
+            let query = search.query.chars.iter().collect::<String>();
+            return format!("no match for {}, esc to go back", query);
+        };
+        let matching = self.matching();
+        let index = matching.iter().filter(|&&line| line < at).count();
+        let none = match search.none {
+            Some(way) => format!(", none {}", way),
+            None => String::new(),
+        };
+        format!(
+            "match {} of {}{}, n for the next, N for the previous, esc to go back",
+            index + 1,
+            matching.len(),
+            none
+        )
+    }
+
+    /// Show the line of the match the search is at
+    fn show_match(&mut self) {
+        if let Some(at) = self.search.as_ref().and_then(|search| search.at) {
+            self.view = Some(at.min(self.bottom()));
+        }
+    }
+
+    /// Show the lines from `top` on, opening the view, or closing it once `top` is at the bottom
+    /// to follow the output again
+    fn show(&mut self, stream: Stream, top: usize) -> Result<()> {
+        if top >= self.bottom() {
+            if self.view.take().is_none() {
+                return Ok(());
+            }
+            self.search = None;
+            return self.close(stream);
+        }
+        self.open()?;
+        self.view = Some(top);
+        self.draw(stream, self.view)
+    }
+
+    /// Draw the overlay or the view, whichever is on top
+    fn redraw(&self, stream: Stream) -> Result<()> {
+        let Some((overlay, status)) = &self.overlay else {
+            return match self.view {
+                Some(top) => self.draw(stream, Some(top)),
+                None => Ok(()),
+            };
+        };
+        let size = pty::window_size().unwrap_or(pty::DEFAULT_SIZE);
+        let (rows, columns) = (size.ws_row as usize, size.ws_col as usize);
+        let mut screen = b"\x1b[H\x1b[2J".to_vec();
+        for line in overlay.iter().take(rows.saturating_sub(1)) {
+            screen.extend(line.chars().take(columns).collect::<String>().bytes());
+            screen.extend_from_slice(b"\r\n");
+        }
+        status_line(&mut screen, status);
+        output::write(stream, &screen)
+    }
+}
+
+/// Whether a line starts a panic, a traceback or an error, as the compilers and runtimes of
+/// common languages write them
+fn starts_error(line: &str) -> bool {
+    let line = line.trim_start();
+    line.contains("panicked at")
+        || line.starts_with("panic: ")
+        || line.starts_with("Traceback (most recent call last)")
+        || line.starts_with("Exception in thread ")
+        || ["error", "Error", "ERROR"].iter().any(|error| {
+            line.strip_prefix(error)
+                .is_some_and(|rest| rest.starts_with([':', '[']))
+        })
+}
+
+/// How many lines some output ends
+fn lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| byte == b'\n').count()
+}
+
+fn rows() -> usize {
+    pty::window_size().unwrap_or(pty::DEFAULT_SIZE).ws_row as usize
+}
+
+/// Add a status line in reverse video at the bottom of the screen
+fn status_line(screen: &mut Vec<u8>, status: &str) {
+    let size = pty::window_size().unwrap_or(pty::DEFAULT_SIZE);
+    let status = format!(" {} ", status)
+        .chars()
+        .take(size.ws_col as usize)
+        .collect::<String>();
+    screen.extend(format!("\x1b[{};1H\x1b[7m{}\x1b[0m", size.ws_row, status).bytes());
+}
+
+/// Keep the last lines of the output of the command from now on
+pub fn enable(limit: usize) {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    scrollback.enabled = true;
+    scrollback.limit = limit;
+}
+
+/// Keep some output of the command written to a stream of hot, and whether it is held back
+/// since the view is open or output is paused
+pub fn record(stream: Stream, bytes: &[u8]) -> bool {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    if scrollback.enabled {
+        scrollback.keep(bytes);
+    }
+    if scrollback.shown() || scrollback.paused {
+        let kept = scrollback.enabled;
+        scrollback.hold(stream, bytes.to_vec(), kept);
+        true
+    } else {
+        false
+    }
+}
+
+/// Hold back a line of hot's own written to a stream of hot while the view or an overlay is
+/// open, without keeping it, or give it back to be written
+pub fn hold_message(stream: Stream, bytes: Vec<u8>) -> Option<Vec<u8>> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    if !scrollback.shown() {
+        return Some(bytes);
+    }
+    scrollback.hold(stream, bytes, false);
+    None
+}
+
+/// Show the lines from the one `to` picks, given the first one shown and the one at the
+/// bottom, unless an overlay is shown or a search typed
+fn go(to: impl FnOnce(Option<usize>, usize) -> usize) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal().filter(|_| scrollback.enabled) else {
+        return Ok(());
+    };
+    let typing = scrollback
+        .search
+        .as_ref()
+        .is_some_and(|search| search.prompt.is_some());
+    if scrollback.overlay.is_some() || typing {
+        return Ok(());
+    }
+    let top = to(scrollback.view, scrollback.bottom());
+    scrollback.show(stream, top)
+}
+
+/// Scroll up by some lines, or down for a negative number, opening the view on the way up and
+/// closing it at the bottom to follow the output again
+pub fn scroll(lines: isize) -> Result<()> {
+    go(|view, bottom| view.unwrap_or(bottom).saturating_add_signed(-lines))
+}
+
+/// Scroll up by some pages of the view, or down for a negative number
+pub fn page(pages: isize) -> Result<()> {
+    scroll(pages * rows().saturating_sub(1).max(1) as isize)
+}
+
+/// Show the first lines kept
+pub fn top() -> Result<()> {
+    go(|_, _| 0)
+}
+
+/// Close the view to follow the output again
+pub fn bottom() -> Result<()> {
+    go(|_, bottom| bottom)
+}
+
+/// Draw the view or the overlay again if one is open, as after the terminal was resized
+pub fn redraw() -> Result<()> {
+    let scrollback = SCROLLBACK.lock().unwrap();
+    match screen::terminal() {
+        Some(stream) => scrollback.redraw(stream),
+        None => Ok(()),
+    }
+}
+
+/// Show some lines over the output with a status line below until `close_overlay`
+pub fn overlay(lines: Vec<String>, status: &'static str) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    scrollback.open()?;
+    scrollback.overlay = Some((lines, status));
+    scrollback.redraw(stream)
+}
+
+/// Whether an overlay is shown, which the next key closes
+pub fn overlaid() -> bool {
+    SCROLLBACK.lock().unwrap().overlay.is_some()
+}
+
+/// Close the overlay, showing what was below it again
+pub fn close_overlay() -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    match scrollback.overlay.take() {
+        Some(_) => scrollback.close(stream),
+        None => Ok(()),
+    }
+}
+
+/// Hold back the output of the command, or write what was held back, returning whether it is
+/// held back now
+pub fn toggle_hold() -> Result<bool> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    scrollback.paused = !scrollback.paused;
+    scrollback.release()?;
+    Ok(scrollback.paused)
+}
+
+/// Whether output is held back, so it has to be copied
+pub fn holding() -> bool {
+    let scrollback = SCROLLBACK.lock().unwrap();
+    scrollback.shown() || scrollback.paused
+}
+
+/// Start a search through the lines kept, opening the view where the output is to type the
+/// query below it, or return false without them or a terminal
+pub fn start_search() -> Result<bool> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal().filter(|_| scrollback.enabled) else {
+        return Ok(false);
+    };
+    let before = match scrollback.search.take() {
+        Some(search) => search.before,
+        None => scrollback.view,
+    };
+    scrollback.open()?;
+    scrollback.view = Some(scrollback.view.unwrap_or(scrollback.bottom()));
+    scrollback.search = Some(Search {
+        query: Query::new(""),
+        at: None,
+        prompt: Some(String::new()),
+        none: None,
+        before,
+    });
+    scrollback.redraw(stream)?;
+    Ok(true)
+}
+
+/// Show the first line matching the query of the search as it is typed, shown as `prompt`
+pub fn search(query: &str, prompt: String) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    let Some(search) = &mut scrollback.search else {
+        return Ok(());
+    };
+    search.query = Query::new(query);
+    search.prompt = Some(prompt);
+    let at = scrollback.matching().first().copied();
+    if let Some(search) = &mut scrollback.search {
+        search.at = at;
+    }
+    scrollback.show_match();
+    scrollback.redraw(stream)
+}
+
+/// Stop typing the query, staying at the match found for `next_match`, or without `keep` end
+/// the search and go back to what was shown before it
+pub fn end_search(keep: bool) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    let Some(search) = &mut scrollback.search else {
+        return Ok(());
+    };
+    if keep {
+        search.prompt = None;
+        return scrollback.redraw(stream);
+    }
+    scrollback.view = search.before;
+    scrollback.search = None;
+    match scrollback.view {
+        Some(_) => scrollback.redraw(stream),
+        None => scrollback.close(stream),
+    }
+}
+
+/// Whether the view shows a search which was typed, so `n` and `N` move between its matches
+pub fn searched() -> bool {
+    let scrollback = SCROLLBACK.lock().unwrap();
+    let typed = scrollback
+        .search
+        .as_ref()
+        .is_some_and(|search| search.prompt.is_none());
+    typed && scrollback.view.is_some() && scrollback.overlay.is_none()
+}
+
+/// Show the next match of the search below the one shown, or above it unless `forward`
+pub fn next_match(forward: bool) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    let matching = scrollback.matching();
+    let Some(search) = &mut scrollback.search else {
+        return Ok(());
+    };
+    let Some(at) = search.at else {
+        return Ok(());
+    };
+    let next = match forward {
+        true => matching.into_iter().find(|&line| line > at),
+        false => matching.into_iter().rev().find(|&line| line < at),
+    };
+    search.none = match next {
+        Some(_) => None,
+        None if forward => Some("below"),
+        None => Some("above"),
+    };
+    search.at = next.or(search.at);
+    scrollback.show_match();
+    scrollback.redraw(stream)
+}
+
+/// Start a run of the command or the build, whose output `run` gives back
+pub fn start_run() {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let start = scrollback.forgotten + scrollback.lines.len();
+    scrollback.runs.push_back(start);
+    if scrollback.runs.len() > 2 {
+        scrollback.runs.pop_front();
+    }
+}
+
+/// The output kept of the latest run, or of the one before it with `back`, without its first
+/// lines once they were forgotten
+pub fn run(back: bool) -> Option<Vec<u8>> {
+    let scrollback = SCROLLBACK.lock().unwrap();
+    if !scrollback.enabled {
+        return None;
+    }
+    let index = scrollback.runs.len().checked_sub(1 + back as usize)?;
+    let lines = scrollback.lines();
+    let line = |start: usize| start.saturating_sub(scrollback.forgotten).min(lines.len());
+    let start = line(scrollback.runs[index]);
+    let end = scrollback
+        .runs
+        .get(index + 1)
+        .map_or(lines.len(), |&end| line(end));
+    Some(lines[start..end].concat())
+}
+
+/// Write output of hot's own to the terminal, held back like the output of the command while
+/// the view or an overlay is open or output is paused
+pub fn write(bytes: Vec<u8>) -> Result<()> {
+    let mut scrollback = SCROLLBACK.lock().unwrap();
+    let Some(stream) = screen::terminal() else {
+        return Ok(());
+    };
+    scrollback.hold(stream, bytes, false);
+    scrollback.release()
+}
+
+/// The last panic, traceback or error in the output kept, up to the blank line after it, with
+/// the line it starts at
+///
+/// A one line error right after another, like cargo's `could not compile`, sums it up so it
+/// goes with it.
+pub fn last_error() -> Option<(usize, Vec<String>)> {
+    let scrollback = SCROLLBACK.lock().unwrap();
+    let lines = scrollback
+        .lines()
+        .into_iter()
+        .map(output::visible)
+        .collect::<Vec<_>>();
+    let end = |start: usize| {
+        let block = lines[start..].iter().take(ERROR_LINES);
+        start + block.take_while(|line| !line.trim().is_empty()).count()
+    };
+    let last = lines.iter().rposition(|line| starts_error(line))?;
+    let mut start = last;
+    if end(last) == last + 1 && last >= 2 && lines[last - 1].trim().is_empty() {
+        let before = lines[..last - 1]
+            .iter()
+            .rposition(|line| starts_error(line));
+        if let Some(before) = before.filter(|&before| end(before) == last - 1) {
+            start = before;
+        }
+    }
+    Some((start, lines[start..end(last)].to_vec()))
+}
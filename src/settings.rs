@@ -0,0 +1,840 @@
+use crate::{
+    config::{self, Config, FromValue, Percent, Size},
+    cron::Schedule,
+    glob::Pattern,
+    keys::{Action, Key, Keymap},
+    limit::Limit,
+    namespace::Namespace,
+    output,
+    priority::{IoNice, Nice},
+    ready::{Dependency, Probe, Url},
+    regex::Regex,
+    signal::Signal,
+    syslog::{self, SystemLog},
+    theme::{self, Theme},
+    timestamp,
+    toml::Value,
+    user::{Group, User},
+    watch::{self, Kind},
+    Stream,
+};
+use ansi_term::Style;
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::{self, Error, ErrorKind, Result},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    str::FromStr,
+    sync::OnceLock,
+    time::Duration,
+};
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+const DEFAULT_CRASH_WINDOW: Duration = Duration::from_secs(1);
+const DEFAULT_CRASH_COUNT: u32 = 5;
+const DEFAULT_KILL_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_READY_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_CPU_FOR: Duration = Duration::from_secs(30);
+const DEFAULT_BUFFER_SIZE: usize = 4096;
+const DEFAULT_BACKPRESSURE: usize = 1024 * 1024;
+const MAX_BUFFER_SIZE: Size = Size(16 << 20);
+
+/// Replaced in the command by the path of the changed file
+const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Environment variable holding the path of the changed file
+pub const CHANGED_FILE_VAR: &str = "HOT_CHANGED_FILE";
+
+/// Whether the command is started again when it exits by itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Restart {
+    /// Exit with the command
+    Never,
+    Always,
+    /// Only start it again after it failed, exit with it when it succeeds
+    OnFailure,
+}
+
+impl Restart {
+    pub fn applies_to(self, status: ExitStatus) -> bool {
+        match self {
+            Restart::Never => false,
+            Restart::Always => true,
+            Restart::OnFailure => !status.success(),
+        }
+    }
+}
+
+impl fmt::Display for Restart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Restart::Never => "never",
+            Restart::Always => "always",
+            Restart::OnFailure => "on-failure",
+        })
+    }
+}
+
+impl FromStr for Restart {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "never" | "no" => Ok(Restart::Never),
+            "always" => Ok(Restart::Always),
+            "on-failure" => Ok(Restart::OnFailure),
+            _ => Err(format!("unknown restart policy '{}'", s)),
+        }
+    }
+}
+
+/// What the command reads as its input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stdin {
+    /// The terminal hot runs in, with the command in the foreground instead of hot
+    Inherit,
+    Null,
+    /// A pipe hot writes to with `interactive`
+    Pipe,
+}
+
+impl FromStr for Stdin {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "inherit" => Ok(Stdin::Inherit),
+            "null" => Ok(Stdin::Null),
+            "pipe" => Ok(Stdin::Pipe),
+            _ => Err(format!("unknown stdin '{}'", s)),
+        }
+    }
+}
+
+/// What rings the bell of the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bell {
+    /// The command exiting with a failure
+    Crash,
+    /// The command exiting at all
+    Exit,
+    /// The command starting again for a reload
+    Reload,
+}
+
+impl FromStr for Bell {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "on-crash" => Ok(Bell::Crash),
+            "on-exit" => Ok(Bell::Exit),
+            "on-reload" => Ok(Bell::Reload),
+            _ => Err(format!(
+                "unknown bell '{}', expected on-crash, on-exit or on-reload",
+                s
+            )),
+        }
+    }
+}
+
+/// When hot writes styles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// When the stream written to is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("unknown color '{}'", s)),
+        }
+    }
+}
+
+impl Color {
+    /// Whether what hot writes to a stream is styled
+    pub fn styles(self, stream: Stream) -> bool {
+        match self {
+            Color::Auto => output::is_terminal(stream),
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+/// The effective options after merging all configuration layers
+pub struct Settings {
+    pub command: Vec<String>,
+    /// The directory the command and the build run in, instead of the one hot runs in
+    pub cwd: Option<PathBuf>,
+    /// Run before the command on every reload, which is only restarted when this succeeds
+    pub build: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// Files to read more environment variables from, overridden by `env`
+    pub env_files: Vec<PathBuf>,
+    pub keymap: Keymap,
+    /// Whether keys are passed on to the command, except after `escape_key`
+    pub interactive: bool,
+    pub escape_key: Key,
+    /// The input of the command when it doesn't run in a pseudo-terminal
+    pub stdin: Stdin,
+    /// Whether hot runs on the alternate screen of its terminal
+    pub alt_screen: bool,
+    /// Whether the screen is cleared before the command or the build starts for a reload
+    pub clear: bool,
+    /// Whether the title of the terminal shows the command and its state
+    pub title: bool,
+    /// What rings the bell of the terminal
+    pub bell: Vec<Bell>,
+    /// Whether the bell is a desktop notification instead
+    pub notify: bool,
+    /// Whether the mouse wheel scrolls back through the output of the command
+    pub mouse: bool,
+    /// How many lines of output are kept to scroll back through
+    pub scrollback: Option<u32>,
+    pub theme: Theme,
+    /// When hot styles its own messages and the output of the command
+    pub color: Color,
+    pub watch: Vec<PathBuf>,
+    /// Whether the executable of the command is watched too
+    pub watch_exe: bool,
+    pub ignore: Vec<Pattern>,
+    /// Whether `.gitignore` and `.ignore` files exclude paths from watching
+    pub gitignore: bool,
+    /// How long files must stop changing before a reload
+    pub debounce: Duration,
+    /// Kinds of changes to watched paths that cause a reload
+    pub events: Vec<Kind>,
+    /// Interval to poll watched paths at instead of using inotify
+    pub poll_watch: Option<Duration>,
+    pub restart: Restart,
+    /// How often the command is restarted after exiting before giving up
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart after the command exited
+    pub backoff: Duration,
+    /// Longest delay between restarts
+    pub backoff_max: Duration,
+    /// An exit this soon after starting counts towards a crash loop
+    pub crash_window: Duration,
+    /// Exits in a row that make a crash loop, which stops restarting
+    pub crash_count: u32,
+    /// Sent to the command to ask it to exit
+    pub stop_signal: Signal,
+    /// Sent to the command on a reload instead of restarting it
+    pub reload_signal: Option<Signal>,
+    /// How long the command has to exit after being asked to before it is killed
+    pub kill_timeout: Duration,
+    /// How long a run of the command may take before it is stopped
+    pub timeout: Option<Duration>,
+    /// How long the command may write no output before it is restarted
+    pub stall_timeout: Option<Duration>,
+    /// Memory the processes of the command may use before it is restarted
+    pub max_memory: Option<Size>,
+    /// CPU usage above which the command is restarted, once it lasted for `max_cpu_for`
+    pub max_cpu: Option<Percent>,
+    pub max_cpu_for: Duration,
+    /// Interval of reloads which happen without any trigger
+    pub every: Option<Duration>,
+    /// Times of reloads which happen without any trigger
+    pub cron: Option<Schedule>,
+    /// How often to run the command before exiting, instead of the restart policy
+    pub times: Option<u32>,
+    /// Whether hot exits by the signal which killed the command
+    pub reraise_signal: bool,
+    /// Where to record the PIDs of hot and the command
+    pub pidfile: Option<PathBuf>,
+    /// Whether the command runs in a pseudo-terminal
+    pub pty: bool,
+    /// How much of the output of the command is read at once
+    pub buffer_size: usize,
+    /// Whether output is written a line at a time
+    pub line_buffered: bool,
+    /// Whether stderr of the command is written to stdout of hot
+    pub merge_output: bool,
+    /// Whether lines of output start with the stream they were written to
+    pub prefix: bool,
+    /// The format of the time lines of output start with, when they do
+    pub timestamps: Option<timestamp::Format>,
+    /// How output of the command which looks binary is shown
+    pub binary: output::Binary,
+    /// The encoding the output of the command is decoded from, unless it is copied as it is
+    pub encoding: Option<output::Encoding>,
+    /// Whether ANSI escape sequences are removed from the output of the command
+    pub strip_ansi: bool,
+    /// Whether `\r\n` line endings in the output of the command are turned into `\n`
+    pub normalize_newlines: bool,
+    /// How many lines of output are shown a second at most, suppressing the rest
+    pub max_lines_per_sec: Option<u32>,
+    /// How much output may wait for hot's stdout and stderr before no more is read
+    pub backpressure: usize,
+    /// Where the output of the command is copied to as well
+    pub system_log: Option<SystemLog>,
+    /// Who the command and the build run as, instead of the user running hot
+    pub user: Option<User>,
+    /// The group they run as, instead of the primary group of `user`
+    pub group: Option<Group>,
+    /// Resource limits set for the command and the build
+    pub limits: Vec<Limit>,
+    /// CPU and I/O priorities of the command and the build
+    pub nice: Option<Nice>,
+    pub ionice: Option<IoNice>,
+    /// Caps on the memory and CPU the processes of the command use together, in a cgroup
+    pub cgroup_mem: Option<Size>,
+    pub cgroup_cpu: Option<Percent>,
+    /// Namespaces the command runs in of its own
+    pub unshare: Vec<Namespace>,
+    /// Addresses hot listens on and passes the sockets of to the command
+    pub listen: Vec<String>,
+    /// Wait between stopping the command and starting it again
+    pub restart_delay: Duration,
+    /// Whether a restart starts the new command before stopping the old one
+    pub overlap: bool,
+    /// How long a new command must be running to be ready when there are no probes
+    pub ready_delay: Duration,
+    /// Probes which must all pass for the command to be ready
+    pub ready: Vec<Probe>,
+    /// Services which must accept connections before the command first starts
+    pub wait_for: Vec<Dependency>,
+}
+
+fn get<T: FromValue>(config: &Config, key: &str) -> Result<Option<T>> {
+    match config.get(key) {
+        Some((value, source)) => T::from_value(value).map(Some).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for '{}' from {}: {}", key, source, err),
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// A setting given by name, like `always` for a restart policy
+fn parse<T: FromStr<Err = String>>(config: &Config, key: &str) -> Result<Option<T>> {
+    match config.get(key) {
+        Some((value, source)) => String::from_value(value)
+            .and_then(|name| name.parse())
+            .map(Some)
+            .map_err(|err| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid value for '{}' from {}: {}", key, source, err),
+                )
+            }),
+        None => Ok(None),
+    }
+}
+
+/// A command given either as a line to split into words or as a list of words
+fn words(config: &Config, key: &str) -> Result<Vec<String>> {
+    match config.get(key) {
+        Some((Value::String(line), source)) => config::split_words(line).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for '{}' from {}: {}", key, source, err),
+            )
+        }),
+        Some(_) => Ok(get(config, key)?.unwrap_or_default()),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn command(config: &Config) -> Result<Vec<String>> {
+    let mut command = words(config, "command")?;
+    command.extend(get::<Vec<String>>(config, "args")?.unwrap_or_default());
+    Ok(command)
+}
+
+/// Environment variables are merged across layers rather than overridden
+fn env(config: &Config) -> Result<BTreeMap<String, String>> {
+    let mut env = BTreeMap::new();
+    for (value, source) in config.get_all("env") {
+        let invalid = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for 'env' from {}: {}", source, err),
+            )
+        };
+        match value {
+            Value::Table(table) => {
+                for (key, value) in table {
+                    env.insert(key.clone(), String::from_value(value).map_err(invalid)?);
+                }
+            }
+            value => {
+                for pair in Vec::<String>::from_value(value).map_err(invalid)? {
+                    let (key, value) = pair
+                        .split_once('=')
+                        .ok_or_else(|| invalid(format!("expected KEY=VALUE, found '{}'", pair)))?;
+                    env.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+    }
+    Ok(env)
+}
+
+/// Lines of a watch list which name a path, stdin being read only once for every reload
+fn read_watch_list(file: &Path) -> Result<Vec<PathBuf>> {
+    static STDIN: OnceLock<String> = OnceLock::new();
+    let text = if file == Path::new("-") {
+        STDIN
+            .get_or_init(|| io::read_to_string(io::stdin()).unwrap_or_default())
+            .clone()
+    } else {
+        fs::read_to_string(file)
+            .map_err(|err| Error::new(err.kind(), format!("{}: {}", file.display(), err)))?
+    };
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Watched paths given directly, through watch lists and for `watch-git`
+fn watch(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut watch = get::<Vec<PathBuf>>(config, "watch")?.unwrap_or_default();
+    for file in get::<Vec<PathBuf>>(config, "watch-list")?.unwrap_or_default() {
+        watch.extend(read_watch_list(&file)?);
+    }
+    if get(config, "watch-git")?.unwrap_or(false) {
+        let paths = watch::git_paths()
+            .map_err(|err| Error::new(err.kind(), format!("watch-git: {}", err)))?;
+        watch.extend(paths);
+    }
+    Ok(watch)
+}
+
+/// Readiness probes of every kind
+fn probes(config: &Config) -> Result<Vec<Probe>> {
+    let tcp = get::<Vec<String>>(config, "ready-tcp")?.unwrap_or_default();
+    let http = get::<Vec<Url>>(config, "ready-http")?.unwrap_or_default();
+    let log = get::<Vec<Regex>>(config, "ready-regex")?.unwrap_or_default();
+    Ok(tcp
+        .into_iter()
+        .map(Probe::Tcp)
+        .chain(http.into_iter().map(Probe::Http))
+        .chain(log.into_iter().map(Probe::Log))
+        .collect())
+}
+
+/// Settings given by name as a list or separated by commas
+fn list<T: FromStr<Err = String>>(config: &Config, key: &str) -> Result<Vec<T>> {
+    let Some((value, source)) = config.get(key) else {
+        return Ok(Vec::new());
+    };
+    let invalid = |err: String| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid value for '{}' from {}: {}", key, source, err),
+        )
+    };
+    let mut items = Vec::new();
+    for names in Vec::<String>::from_value(value).map_err(invalid)? {
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            items.push(name.parse().map_err(invalid)?);
+        }
+    }
+    Ok(items)
+}
+
+/// Kinds of changes to reload for, every kind when none are given
+fn events(config: &Config) -> Result<Vec<Kind>> {
+    let kinds = list(config, "events")?;
+    if kinds.is_empty() {
+        return Ok(Kind::ALL.to_vec());
+    }
+    Ok(kinds)
+}
+
+/// Bindings from each layer's `[keys]` table replace the defaults per action
+fn keymap(config: &Config) -> Result<Keymap> {
+    let mut keymap = Keymap::default();
+    for (value, source) in config.get_all("keys") {
+        let invalid = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for 'keys' from {}: {}", source, err),
+            )
+        };
+        let table = match value {
+            Value::Table(table) => table,
+            value => {
+                return Err(invalid(format!(
+                    "expected a table, found {}",
+                    value.type_name()
+                )))
+            }
+        };
+        for (action, keys) in table {
+            let action = action.parse().map_err(invalid)?;
+            let keys = Vec::<String>::from_value(keys)
+                .map_err(invalid)?
+                .iter()
+                .map(|key| key.parse())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(invalid)?;
+            keymap.bind(action, keys);
+        }
+    }
+    Ok(keymap)
+}
+
+/// `color`, or else `CLICOLOR_FORCE` or `NO_COLOR` when they are set
+fn color(config: &Config) -> Result<Color> {
+    if let Some(color) = parse(config, "color")? {
+        return Ok(color);
+    }
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    if var("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+        Ok(Color::Always)
+    } else if var("NO_COLOR").is_some() {
+        Ok(Color::Never)
+    } else {
+        Ok(Color::Auto)
+    }
+}
+
+/// Styles from each layer's `[theme]` table replace the defaults per element
+///
+/// hot's own messages go to stderr, and are plain when `color` doesn't style it.
+fn theme(config: &Config, color: Color) -> Result<Theme> {
+    let mut theme = Theme::default();
+    for (value, source) in config.get_all("theme") {
+        let invalid = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid value for 'theme' from {}: {}", source, err),
+            )
+        };
+        let table = match value {
+            Value::Table(table) => table,
+            value => {
+                return Err(invalid(format!(
+                    "expected a table, found {}",
+                    value.type_name()
+                )))
+            }
+        };
+        for (element, style) in table {
+            let style = String::from_value(style).map_err(invalid)?;
+            let style = theme::parse_style(&style).map_err(invalid)?;
+            theme.set(element, style).map_err(invalid)?;
+        }
+    }
+    for (key, element) in [("stdout-style", "stdout"), ("stderr-style", "stderr")] {
+        let Some((value, source)) = config.get(key) else {
+            continue;
+        };
+        let style = String::from_value(value)
+            .and_then(|style| theme::parse_style(&style))
+            .map_err(|err| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid value for '{}' from {}: {}", key, source, err),
+                )
+            })?;
+        theme.set(element, style).unwrap();
+    }
+    if !color.styles(Stream::Stderr) {
+        theme = Theme {
+            stdout: theme.stdout,
+            stderr: theme.stderr,
+            ..Theme::plain()
+        };
+    }
+    Ok(theme)
+}
+
+/// The user to run as, which needs a group when it has no entry to take the primary group from
+fn user(config: &Config, group: Option<Group>) -> Result<Option<User>> {
+    let user = parse::<User>(config, "user")?;
+    if let Some(User { uid, gid: None, .. }) = &user {
+        if group.is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("user {} has no primary group, give one with 'group'", uid),
+            ));
+        }
+    }
+    Ok(user)
+}
+
+/// The system log, naming the command after its program
+fn system_log(config: &Config, command: &[String]) -> Result<Option<SystemLog>> {
+    let Some(kind) = parse::<syslog::Kind>(config, "system-log")? else {
+        return Ok(None);
+    };
+    let program = command.first().map_or("hot", |program| {
+        Path::new(program)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(program)
+    });
+    SystemLog::connect(kind, program).map(Some)
+}
+
+/// The timestamp format, with `timestamps` or when one is given
+fn timestamps(config: &Config) -> Result<Option<timestamp::Format>> {
+    let format = parse(config, "timestamp-format")?;
+    if format.is_some() || get(config, "timestamps")?.unwrap_or(false) {
+        return Ok(Some(format.unwrap_or_default()));
+    }
+    Ok(None)
+}
+
+fn buffer_size(config: &Config) -> Result<usize> {
+    match get::<Size>(config, "buffer-size")? {
+        None => Ok(DEFAULT_BUFFER_SIZE),
+        Some(size) if size.0 > 0 && size <= MAX_BUFFER_SIZE => Ok(size.0 as usize),
+        Some(_) => {
+            let (_, source) = config.get("buffer-size").unwrap();
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "invalid value for 'buffer-size' from {}: expected a size from 1 to {}",
+                    source, MAX_BUFFER_SIZE
+                ),
+            ))
+        }
+    }
+}
+
+impl Settings {
+    pub fn resolve(config: &Config) -> Result<Self> {
+        let command = command(config)?;
+        let group = parse(config, "group")?;
+        let color = color(config)?;
+        Ok(Self {
+            user: user(config, group)?,
+            group,
+            limits: get(config, "limit")?.unwrap_or_default(),
+            nice: parse(config, "nice")?,
+            ionice: parse(config, "ionice")?,
+            cgroup_mem: get(config, "cgroup-mem")?,
+            cgroup_cpu: get(config, "cgroup-cpu")?,
+            unshare: list(config, "unshare")?,
+            listen: get(config, "listen")?.unwrap_or_default(),
+            system_log: system_log(config, &command)?,
+            command,
+            cwd: get(config, "cwd")?,
+            build: words(config, "build")?,
+            env: env(config)?,
+            env_files: get(config, "env-file")?.unwrap_or_default(),
+            keymap: keymap(config)?,
+            interactive: get(config, "interactive")?.unwrap_or(false),
+            escape_key: parse(config, "escape-key")?.unwrap_or_else(Key::escape),
+            stdin: parse(config, "stdin")?.unwrap_or(Stdin::Pipe),
+            alt_screen: get(config, "alt-screen")?.unwrap_or(false),
+            clear: get(config, "clear")?.unwrap_or(false),
+            title: get(config, "title")?.unwrap_or(false),
+            bell: list(config, "bell")?,
+            notify: get(config, "notify")?.unwrap_or(false),
+            mouse: get(config, "mouse")?.unwrap_or(false),
+            scrollback: get(config, "scrollback")?,
+            theme: theme(config, color)?,
+            color,
+            watch: watch(config)?,
+            watch_exe: get(config, "watch-exe")?.unwrap_or(false),
+            ignore: get(config, "ignore")?.unwrap_or_default(),
+            gitignore: !get(config, "no-gitignore")?.unwrap_or(false),
+            debounce: get(config, "debounce")?.unwrap_or(DEFAULT_DEBOUNCE),
+            poll_watch: get(config, "poll-watch")?,
+            events: events(config)?,
+            restart: parse(config, "restart")?.unwrap_or(Restart::Never),
+            max_restarts: get(config, "max-restarts")?,
+            backoff: get(config, "backoff")?.unwrap_or(DEFAULT_BACKOFF),
+            backoff_max: get(config, "backoff-max")?.unwrap_or(DEFAULT_BACKOFF_MAX),
+            crash_window: get(config, "crash-window")?.unwrap_or(DEFAULT_CRASH_WINDOW),
+            crash_count: get(config, "crash-count")?.unwrap_or(DEFAULT_CRASH_COUNT),
+            stop_signal: parse(config, "stop-signal")?.unwrap_or(Signal::TERM),
+            reload_signal: parse(config, "reload-signal")?,
+            kill_timeout: get(config, "kill-timeout")?.unwrap_or(DEFAULT_KILL_TIMEOUT),
+            timeout: get(config, "timeout")?,
+            stall_timeout: get(config, "stall-timeout")?,
+            max_memory: get(config, "max-memory")?,
+            max_cpu: get(config, "max-cpu")?,
+            max_cpu_for: get(config, "max-cpu-for")?.unwrap_or(DEFAULT_MAX_CPU_FOR),
+            every: get(config, "every")?,
+            cron: parse(config, "cron")?,
+            times: get(config, "times")?,
+            reraise_signal: get(config, "reraise-signal")?.unwrap_or(false),
+            pidfile: get(config, "pidfile")?,
+            pty: get(config, "pty")?.unwrap_or(false),
+            buffer_size: buffer_size(config)?,
+            line_buffered: get(config, "line-buffered")?.unwrap_or(false),
+            merge_output: get(config, "merge-output")?.unwrap_or(false),
+            prefix: get(config, "prefix")?.unwrap_or(false),
+            timestamps: timestamps(config)?,
+            binary: parse(config, "binary")?.unwrap_or(output::Binary::Show),
+            encoding: parse(config, "encoding")?,
+            strip_ansi: get(config, "strip-ansi")?.unwrap_or(false),
+            normalize_newlines: get(config, "normalize-newlines")?.unwrap_or(false),
+            max_lines_per_sec: get(config, "max-lines-per-sec")?,
+            backpressure: get::<Size>(config, "backpressure")?
+                .map_or(DEFAULT_BACKPRESSURE, |size| size.0 as usize),
+            restart_delay: get(config, "restart-delay")?.unwrap_or_default(),
+            overlap: get(config, "overlap")?.unwrap_or(false),
+            ready_delay: get(config, "ready-delay")?.unwrap_or(DEFAULT_READY_DELAY),
+            ready: probes(config)?,
+            wait_for: get(config, "wait-for")?.unwrap_or_default(),
+        })
+    }
+
+    /// The file the command runs, looked up in `PATH` unless it names a path, which is relative
+    /// to `cwd`
+    pub fn executable(&self) -> Option<PathBuf> {
+        let program = Path::new(self.command.first()?);
+        if program.components().count() > 1 {
+            return Some(match &self.cwd {
+                Some(cwd) => cwd.join(program),
+                None => program.to_owned(),
+            });
+        }
+        let path = match self.env.get("PATH") {
+            Some(path) => path.into(),
+            None => env::var_os("PATH")?,
+        };
+        env::split_paths(&path)
+            .map(|dir| dir.join(program))
+            .find(|file| {
+                file.metadata()
+                    .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            })
+    }
+
+    /// Paths to watch for changes, including the executable with `watch-exe`
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.watch.clone();
+        if self.watch_exe {
+            paths.extend(self.executable());
+        }
+        paths
+    }
+
+    /// Whether the watcher for `other` would notice the same changes
+    pub fn watches_same(&self, other: &Settings) -> bool {
+        self.watch_paths() == other.watch_paths()
+            && self.ignore == other.ignore
+            && self.gitignore == other.gitignore
+            && self.poll_watch == other.poll_watch
+            && self.events == other.events
+    }
+
+    /// A changed file as the command sees it, made absolute when it runs in another directory
+    pub fn changed_path(&self, changed: &Path) -> PathBuf {
+        match env::current_dir() {
+            Ok(dir) if self.cwd.is_some() => dir.join(changed),
+            _ => changed.to_owned(),
+        }
+    }
+
+    /// The command with `{file}` replaced by the changed file that caused a reload
+    pub fn command_for(&self, changed: Option<&Path>) -> Vec<String> {
+        substitute(&self.command, changed)
+    }
+
+    /// The build command with `{file}` replaced like in the command
+    pub fn build_for(&self, changed: Option<&Path>) -> Vec<String> {
+        substitute(&self.build, changed)
+    }
+
+    /// How to press the key bound to an action, for hot's messages, like `'r'` or `'ctrl-a r'`
+    /// after the escape key with `interactive`
+    pub fn key_for(&self, action: Action) -> String {
+        match self.keymap.key(action) {
+            Some(key) if self.interactive => format!("'{} {}'", self.escape_key, key),
+            Some(key) => format!("'{}'", key),
+            None => format!("a key bound to {} in [keys]", action.name()),
+        }
+    }
+
+    /// Whether the command reads the terminal, which hot then leaves to it rather than
+    /// reading keys
+    pub fn command_reads_terminal(&self) -> bool {
+        self.stdin == Stdin::Inherit && !self.pty
+    }
+
+    /// How many lines of output are kept to scroll back through, 10,000 for the mouse wheel
+    /// unless `scrollback` says otherwise, if any are
+    pub fn kept_lines(&self) -> Option<usize> {
+        let lines = self.scrollback.or(self.mouse.then_some(10_000))?;
+        (lines > 0).then_some(lines as usize)
+    }
+
+    /// Whether output of a stream of the command reaches the same stream of hot unchanged and
+    /// isn't looked at on the way
+    pub fn output_untouched(&self, stream: Stream) -> bool {
+        let logged = self.system_log.is_some()
+            || self.line_buffered
+            || self.prefix
+            || self.timestamps.is_some()
+            || self.binary != output::Binary::Show
+            || self.encoding.is_some()
+            || self.strip_ansi
+            || self.normalize_newlines
+            || self.max_lines_per_sec.is_some()
+            || self.kept_lines().is_some()
+            || self
+                .ready
+                .iter()
+                .any(|probe| matches!(probe, Probe::Log(_)));
+        let moved = stream == Stream::Stderr && self.merge_output;
+        !logged && !moved && self.output_style(stream).is_plain()
+    }
+
+    /// The style output of a stream of the command is written in, plain unless `color` styles
+    /// the stream hot writes it to, so by default the output stays as it is in files and pipes
+    pub fn output_style(&self, stream: Stream) -> Style {
+        let (style, to) = match stream {
+            Stream::Stdout => (self.theme.stdout, Stream::Stdout),
+            Stream::Stderr if self.merge_output => (self.theme.stderr, Stream::Stdout),
+            Stream::Stderr => (self.theme.stderr, Stream::Stderr),
+        };
+        if self.color.styles(to) {
+            style
+        } else {
+            Style::new()
+        }
+    }
+
+    /// The command quoted for display
+    pub fn command_line(&self) -> String {
+        quote_command(&self.command)
+    }
+}
+
+/// Replace `{file}` by the changed file, where a word that is just `{file}` is left out when
+/// there is none
+fn substitute(command: &[String], changed: Option<&Path>) -> Vec<String> {
+    let file = changed.map(|path| path.to_string_lossy());
+    command
+        .iter()
+        .filter(|word| file.is_some() || *word != FILE_PLACEHOLDER)
+        .map(|word| word.replace(FILE_PLACEHOLDER, file.as_deref().unwrap_or_default()))
+        .collect()
+}
+
+/// A command quoted for display
+pub fn quote_command(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|word| config::quote_word(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,123 @@
+//! Unix signals by name
+
+use std::{fmt, io, mem, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signal(pub libc::c_int);
+
+const NAMES: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ABRT", libc::SIGABRT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// The signals offered by the signal menu, by the key picking them
+pub const MENU: &[(char, Signal)] = &[
+    ('h', Signal(libc::SIGHUP)),
+    ('i', Signal(libc::SIGINT)),
+    ('q', Signal(libc::SIGQUIT)),
+    ('t', Signal(libc::SIGTERM)),
+    ('k', Signal(libc::SIGKILL)),
+    ('1', Signal(libc::SIGUSR1)),
+    ('2', Signal(libc::SIGUSR2)),
+    ('a', Signal(libc::SIGALRM)),
+    ('w', Signal(libc::SIGWINCH)),
+];
+
+impl Signal {
+    pub const TERM: Signal = Signal(libc::SIGTERM);
+    pub const KILL: Signal = Signal(libc::SIGKILL);
+    pub const STOP: Signal = Signal(libc::SIGSTOP);
+    pub const CONT: Signal = Signal(libc::SIGCONT);
+
+    /// Send the signal to every process of a process group, which is fine to have exited
+    pub fn send_group(self, pgid: u32) -> io::Result<()> {
+        if unsafe { libc::kill(-(pgid as libc::pid_t), self.0) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether any process of a process group is still running
+///
+/// Processes of the group which exited are reaped, hot being their subreaper.
+pub fn group_exists(pgid: u32) -> bool {
+    let pgid = pgid as libc::pid_t;
+    while unsafe { libc::waitpid(-pgid, std::ptr::null_mut(), libc::WNOHANG) } > 0 {}
+    unsafe { libc::kill(-pgid, 0) == 0 }
+}
+
+/// Whether a process is still running, or a zombie
+pub fn is_running(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+/// Reap exited children other than `children`, which are left to be waited for by their owners
+pub fn reap_orphans(children: &[u32]) {
+    loop {
+        let mut info = unsafe { mem::zeroed::<libc::siginfo_t>() };
+        let options = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+        if unsafe { libc::waitid(libc::P_ALL, 0, &mut info, options) } < 0 {
+            return;
+        }
+        let pid = unsafe { info.si_pid() };
+        if pid == 0 || children.contains(&(pid as u32)) {
+            return;
+        }
+        unsafe { libc::waitpid(pid, std::ptr::null_mut(), libc::WNOHANG) };
+    }
+}
+
+/// Have processes orphaned by the command reparented to hot rather than init, so they can be
+/// reaped as soon as they exit
+pub fn become_subreaper() -> io::Result<()> {
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Accepts names with or without the `SIG` prefix in any case, like `SIGINT` or `int`, and
+/// numbers
+impl FromStr for Signal {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(number) = s.parse() {
+            return Ok(Signal(number));
+        }
+        let upper = s.to_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|&(_, number)| Signal(number))
+            .ok_or_else(|| format!("unknown signal '{}'", s))
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMES.iter().find(|(_, number)| *number == self.0) {
+            Some((name, _)) => write!(f, "SIG{}", name),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
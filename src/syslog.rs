@@ -0,0 +1,99 @@
+//! Copying the output of the command to the system log, through journald or syslog
+
+use crate::Stream;
+use std::{
+    cell::RefCell,
+    io::{Error, Result},
+    os::unix::net::UnixDatagram,
+    str::FromStr,
+};
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+/// Longest line sent as one entry, longer lines are split
+const MAX_LINE: usize = 4096;
+/// The priorities of lines of stdout and stderr
+const INFO: u8 = 6;
+const ERR: u8 = 3;
+/// The facility of syslog messages, `user`
+const USER: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Journald,
+    Syslog,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "journald" => Ok(Kind::Journald),
+            "syslog" => Ok(Kind::Syslog),
+            _ => Err(format!("unknown system log '{}'", s)),
+        }
+    }
+}
+
+pub struct SystemLog {
+    kind: Kind,
+    socket: UnixDatagram,
+    /// Names the command in the log
+    identifier: String,
+    /// The unfinished last line of stdout and of stderr
+    lines: RefCell<[Vec<u8>; 2]>,
+}
+
+impl SystemLog {
+    pub fn connect(kind: Kind, identifier: &str) -> Result<Self> {
+        let path = match kind {
+            Kind::Journald => JOURNALD_SOCKET,
+            Kind::Syslog => SYSLOG_SOCKET,
+        };
+        let socket = UnixDatagram::unbound()?;
+        socket
+            .connect(path)
+            .map_err(|err| Error::new(err.kind(), format!("{}: {}", path, err)))?;
+        // lines are dropped rather than holding up the command when the log can't keep up
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            kind,
+            socket,
+            identifier: identifier.to_owned(),
+            lines: RefCell::new([Vec::new(), Vec::new()]),
+        })
+    }
+
+    /// Send the lines of some output of the command, with stderr as errors
+    pub fn output(&self, stream: Stream, bytes: &[u8]) {
+        let mut lines = self.lines.borrow_mut();
+        let line = &mut lines[stream as usize];
+        for &byte in bytes {
+            if byte != b'\n' {
+                line.push(byte);
+                if line.len() < MAX_LINE {
+                    continue;
+                }
+            }
+            self.send(stream, line);
+            line.clear();
+        }
+    }
+
+    fn send(&self, stream: Stream, line: &[u8]) {
+        let priority = match stream {
+            Stream::Stdout => INFO,
+            Stream::Stderr => ERR,
+        };
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        let message = match self.kind {
+            Kind::Journald => format!(
+                "PRIORITY={}\nSYSLOG_IDENTIFIER={}\nMESSAGE={}\n",
+                priority, self.identifier, line
+            ),
+            Kind::Syslog => format!("<{}>{}: {}", USER * 8 + priority, self.identifier, line),
+        };
+        let _ = self.socket.send(message.as_bytes());
+    }
+}
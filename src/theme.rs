@@ -0,0 +1,117 @@
+use ansi_term::{Colour, Style};
+
+/// Styles for everything hot prints around the output of the command
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// The command line printed before each run
+    pub banner: Style,
+    /// The reload marker
+    pub reload: Style,
+    /// Output of the command on stdout
+    pub stdout: Style,
+    /// Output of the command on stderr
+    pub stderr: Style,
+    /// Status messages from hot itself
+    pub status: Style,
+    /// Messages that need attention, like a crash loop
+    pub alert: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            banner: Style::new().bold(),
+            reload: Style::new().bold(),
+            stdout: Style::new(),
+            stderr: Style::new(),
+            status: Style::new().bold(),
+            alert: Colour::Red.bold(),
+        }
+    }
+}
+
+impl Theme {
+    /// The names of the elements, as in a `[theme]` table
+    pub const ELEMENTS: &'static [&'static str] =
+        &["banner", "reload", "stdout", "stderr", "status", "alert"];
+
+    /// No styles at all
+    pub fn plain() -> Self {
+        Self {
+            banner: Style::new(),
+            reload: Style::new(),
+            stdout: Style::new(),
+            stderr: Style::new(),
+            status: Style::new(),
+            alert: Style::new(),
+        }
+    }
+
+    /// Override the style of a theme element by name
+    pub fn set(&mut self, element: &str, style: Style) -> Result<(), String> {
+        let slot = match element {
+            "banner" => &mut self.banner,
+            "reload" => &mut self.reload,
+            "stdout" => &mut self.stdout,
+            "stderr" => &mut self.stderr,
+            "status" => &mut self.status,
+            "alert" => &mut self.alert,
+            _ => return Err(format!("unknown theme element '{}'", element)),
+        };
+        *slot = style;
+        Ok(())
+    }
+}
+
+fn parse_colour(word: &str) -> Option<Colour> {
+    let colour = match word {
+        "black" => Colour::Black,
+        "red" => Colour::Red,
+        "green" => Colour::Green,
+        "yellow" => Colour::Yellow,
+        "blue" => Colour::Blue,
+        "purple" | "magenta" => Colour::Purple,
+        "cyan" => Colour::Cyan,
+        "white" => Colour::White,
+        _ => {
+            if let Some(hex) = word.strip_prefix('#').filter(|hex| hex.len() == 6) {
+                let rgb = u32::from_str_radix(hex, 16).ok()?;
+                Colour::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+            } else {
+                Colour::Fixed(word.parse().ok()?)
+            }
+        }
+    };
+    Some(colour)
+}
+
+/// Parse a style like `bold yellow`, `dim`, `italic #ff8800 on black` or `none`
+pub fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    let mut words = s.split_whitespace().map(str::to_lowercase);
+    while let Some(word) = words.next() {
+        style = match word.as_str() {
+            "none" | "plain" => Style::new(),
+            "bold" => style.bold(),
+            "dim" | "dimmed" => style.dimmed(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            "blink" => style.blink(),
+            "reverse" => style.reverse(),
+            "hidden" => style.hidden(),
+            "strikethrough" => style.strikethrough(),
+            "on" => {
+                let colour = words
+                    .next()
+                    .and_then(|word| parse_colour(&word))
+                    .ok_or_else(|| format!("expected a background colour in '{}'", s))?;
+                style.on(colour)
+            }
+            word => match parse_colour(word) {
+                Some(colour) => style.fg(colour),
+                None => return Err(format!("unknown style '{}'", word)),
+            },
+        };
+    }
+    Ok(style)
+}
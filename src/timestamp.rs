@@ -0,0 +1,77 @@
+//! Timestamps in local time for lines of output, like `14:03:27.512`
+
+use std::{
+    ffi::CString,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+extern "C" {
+    // not bound by libc for Linux
+    fn strftime(
+        s: *mut libc::c_char,
+        max: libc::size_t,
+        format: *const libc::c_char,
+        tm: *const libc::tm,
+    ) -> libc::size_t;
+}
+
+/// A strftime format, with `%L` for milliseconds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Format(String);
+
+impl Default for Format {
+    fn default() -> Self {
+        Self("%H:%M:%S.%L".into())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.contains('\0') {
+            return Err(format!("invalid timestamp format '{}'", s.escape_debug()));
+        }
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl Format {
+    /// The current time in this format
+    pub fn now(&self) -> String {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut format = String::with_capacity(self.0.len());
+        let mut chars = self.0.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('%', Some('L')) => {
+                    chars.next();
+                    format.push_str(&format!("{:03}", elapsed.subsec_millis()));
+                }
+                ('%', Some('%')) => {
+                    chars.next();
+                    format.push_str("%%");
+                }
+                _ => format.push(c),
+            }
+        }
+        let Ok(format) = CString::new(format) else {
+            return String::new();
+        };
+        let seconds = elapsed.as_secs() as libc::time_t;
+        let mut time = unsafe { std::mem::zeroed::<libc::tm>() };
+        let mut buffer = [0u8; 256];
+        let written = unsafe {
+            libc::localtime_r(&seconds, &mut time);
+            strftime(
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                format.as_ptr(),
+                &time,
+            )
+        };
+        String::from_utf8_lossy(&buffer[..written]).into_owned()
+    }
+}
@@ -0,0 +1,445 @@
+//! A small parser for the subset of TOML used by hot's configuration files
+//!
+//! Supports tables, dotted keys, basic and literal strings (including their multi-line
+//! forms), integers, floats, booleans, arrays and inline tables. Dates are not supported.
+
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Value::Table(table) => {
+                f.write_str("{ ")?;
+                for (idx, (key, value)) in table.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{} = {}", key, value)?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, msg: impl Into<String>) -> Result<T> {
+        Err(Error {
+            line: self.line,
+            msg: msg.into(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.next() {
+            Some(found) if found == c => Ok(()),
+            Some(found) => self.error(format!("expected '{}', found '{}'", c, found)),
+            None => self.error(format!("expected '{}', found end of file", c)),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), Some('\n') | None) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skip whitespace, newlines and comments
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        self.eat('\r');
+        match self.next() {
+            Some('\n') | None => Ok(()),
+            Some(c) => self.error(format!("expected end of line, found '{}'", c)),
+        }
+    }
+
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => {
+                    self.next();
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.next();
+                    self.literal_string()?
+                }
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            part.push(c);
+                            self.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    if part.is_empty() {
+                        return self.error("expected a key");
+                    }
+                    part
+                }
+            };
+            path.push(part);
+            self.skip_spaces();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<()> {
+        let c = match self.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('e') => '\u{1b}',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let hex = (0..len).filter_map(|_| self.next()).collect::<String>();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error(format!("invalid unicode escape '\\{}{}'", u, hex)),
+                }
+            }
+            Some(c) => return self.error(format!("invalid escape '\\{}'", c)),
+            None => return self.error("unterminated string"),
+        };
+        out.push(c);
+        Ok(())
+    }
+
+    /// Parse a basic string after its opening quote
+    fn basic_string(&mut self) -> Result<String> {
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(out),
+                Some('\\') => self.escape(&mut out)?,
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// Parse a literal string after its opening quote
+    fn literal_string(&mut self) -> Result<String> {
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(out),
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// Parse a multi-line string after its opening triple quote
+    fn multiline_string(&mut self, quote: char) -> Result<String> {
+        let mut out = String::new();
+        // a newline immediately following the opening delimiter is trimmed
+        self.eat('\r');
+        self.eat('\n');
+        loop {
+            match self.next() {
+                Some(c) if c == quote => {
+                    if self.eat(quote) {
+                        if self.eat(quote) {
+                            return Ok(out);
+                        }
+                        out.push(quote);
+                    }
+                    out.push(quote);
+                }
+                Some('\\') if quote == '"' => {
+                    if matches!(self.peek(), Some('\n' | '\r' | ' ' | '\t')) {
+                        // line ending backslash trims all following whitespace
+                        while matches!(self.peek(), Some('\n' | '\r' | ' ' | '\t')) {
+                            self.next();
+                        }
+                    } else {
+                        self.escape(&mut out)?;
+                    }
+                }
+                Some(c) => out.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<String> {
+        if self.eat(quote) {
+            if self.eat(quote) {
+                return self.multiline_string(quote);
+            }
+            return Ok(String::new());
+        }
+        if quote == '"' {
+            self.basic_string()
+        } else {
+            self.literal_string()
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(',') {
+                self.skip_blank();
+                self.expect(']')?;
+                return Ok(Value::Array(values));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        let mut table = Table::new();
+        self.skip_spaces();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            let key = self.key()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            self.insert(&mut table, &key, value)?;
+            self.skip_spaces();
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(Value::Table(table));
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Value> {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.') {
+                word.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        match word.as_str() {
+            "" => return self.error("expected a value"),
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            "inf" | "+inf" => return Ok(Value::Float(f64::INFINITY)),
+            "-inf" => return Ok(Value::Float(f64::NEG_INFINITY)),
+            "nan" | "+nan" | "-nan" => return Ok(Value::Float(f64::NAN)),
+            _ => {}
+        }
+        let digits = word.replace('_', "");
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+        };
+        let radix = match unsigned.get(..2) {
+            Some("0x") => Some(16),
+            Some("0o") => Some(8),
+            Some("0b") => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            if let Ok(int) = i64::from_str_radix(&unsigned[2..], radix) {
+                return Ok(Value::Integer(sign * int));
+            }
+        } else if let Ok(int) = digits.parse::<i64>() {
+            return Ok(Value::Integer(int));
+        } else if let Ok(float) = digits.parse::<f64>() {
+            return Ok(Value::Float(float));
+        }
+        self.error(format!("invalid value '{}'", word))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.next();
+                Ok(Value::String(self.string(quote)?))
+            }
+            Some('[') => {
+                self.next();
+                self.array()
+            }
+            Some('{') => {
+                self.next();
+                self.inline_table()
+            }
+            _ => self.scalar(),
+        }
+    }
+
+    fn insert(&self, table: &mut Table, key: &[String], value: Value) -> Result<()> {
+        let (last, parents) = key.split_last().expect("keys are never empty");
+        let table = self.descend(table, parents)?;
+        if table.contains_key(last) {
+            return self.error(format!("duplicate key '{}'", key.join(".")));
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    fn descend<'t>(&self, mut table: &'t mut Table, path: &[String]) -> Result<&'t mut Table> {
+        for part in path {
+            let entry = table
+                .entry(part.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match entry {
+                Value::Table(table) => table,
+                _ => return self.error(format!("key '{}' is not a table", part)),
+            };
+        }
+        Ok(table)
+    }
+
+    fn document(&mut self) -> Result<Table> {
+        let mut root = Table::new();
+        let mut current = Vec::new();
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.next();
+                    current = self.key()?;
+                    self.expect(']')?;
+                    self.descend(&mut root, &current)?;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    let table = self.descend(&mut root, &current)?;
+                    self.insert(table, &key, value)?;
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Table> {
+    Parser {
+        chars: input.chars().peekable(),
+        line: 1,
+    }
+    .document()
+}
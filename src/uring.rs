@@ -0,0 +1,358 @@
+//! An io_uring reading the output of the command, so the kernel waits for output and reads it
+//! rather than hot waiting for readiness and then reading, with the `io-uring` feature
+//!
+//! Reads of both streams are kept in flight, and a batch of them is collected and submitted
+//! again with a single system call.
+
+use crate::Stream;
+use std::{
+    io::{self, Result},
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+const ENTRIES: u32 = 4;
+
+const OFF_SQ_RING: libc::off_t = 0;
+const OFF_CQ_RING: libc::off_t = 0x8000000;
+const OFF_SQES: libc::off_t = 0x10000000;
+const ENTER_GETEVENTS: libc::c_uint = 1;
+const OP_READ: u8 = 22;
+
+/// `struct io_sqring_offsets`
+#[repr(C)]
+#[derive(Default)]
+struct SubmissionOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+/// `struct io_cqring_offsets`
+#[repr(C)]
+#[derive(Default)]
+struct CompletionOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+/// `struct io_uring_params`
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SubmissionOffsets,
+    cq_off: CompletionOffsets,
+}
+
+/// `struct io_uring_sqe`
+#[repr(C)]
+struct Submission {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    _pad: u64,
+}
+
+/// `struct io_uring_cqe`
+#[repr(C)]
+struct Completion {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+fn check(result: libc::c_long) -> Result<libc::c_long> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(result)
+}
+
+/// Memory shared with the kernel
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: RawFd, len: usize, offset: libc::off_t) -> Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { self.ptr.add(offset as usize).cast() }
+    }
+
+    fn atomic(&self, offset: u32) -> &AtomicU32 {
+        unsafe { &*self.at::<AtomicU32>(offset) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+/// A ring reading the stdout and stderr of one run of the command
+pub struct Ring {
+    fd: OwnedFd,
+    params: Params,
+    submissions: Mapping,
+    completions: Mapping,
+    entries: Mapping,
+    /// The pipes or pseudo-terminals read, by stream
+    sources: [RawFd; 2],
+    buffers: [Box<[u8]>; 2],
+    /// Whether a read into the buffer of a stream is in flight
+    reading: [bool; 2],
+    /// Whether a stream reached its end
+    ended: [bool; 2],
+    /// Reads queued but not submitted yet
+    queued: u32,
+    /// The stream whose output was handed out last, read again on the next call to `next`
+    handed: Option<usize>,
+}
+
+const STREAMS: [Stream; 2] = [Stream::Stdout, Stream::Stderr];
+
+impl Ring {
+    /// Set up a ring for reading the stdout and stderr of the command, `size` at a time
+    pub fn new(stdout: RawFd, stderr: RawFd, size: usize) -> Result<Self> {
+        let mut params = Params::default();
+        let fd = check(unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                ENTRIES,
+                &mut params as *mut Params,
+            )
+        })?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+        let raw = fd.as_raw_fd();
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+        let cq_len =
+            params.cq_off.cqes as usize + params.cq_entries as usize * mem::size_of::<Completion>();
+        let entries_len = params.sq_entries as usize * mem::size_of::<Submission>();
+        Ok(Self {
+            submissions: Mapping::new(raw, sq_len, OFF_SQ_RING)?,
+            completions: Mapping::new(raw, cq_len, OFF_CQ_RING)?,
+            entries: Mapping::new(raw, entries_len, OFF_SQES)?,
+            fd,
+            params,
+            sources: [stdout, stderr],
+            buffers: [
+                vec![0; size].into_boxed_slice(),
+                vec![0; size].into_boxed_slice(),
+            ],
+            reading: [false; 2],
+            ended: [false; 2],
+            queued: 0,
+            handed: None,
+        })
+    }
+
+    /// The descriptor of the ring, readable once reads completed
+    pub fn fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Queue a read of a stream into its buffer, unless one is in flight or it ended
+    fn queue(&mut self, index: usize) {
+        if self.reading[index] || self.ended[index] {
+            return;
+        }
+        let offsets = &self.params.sq_off;
+        let mask = unsafe { *self.submissions.at::<u32>(offsets.ring_mask) };
+        let tail = self
+            .submissions
+            .atomic(offsets.tail)
+            .load(Ordering::Acquire);
+        let slot = tail & mask;
+        let buffer = &mut self.buffers[index];
+        let entry = Submission {
+            opcode: OP_READ,
+            flags: 0,
+            ioprio: 0,
+            fd: self.sources[index],
+            // the current position, as pipes have no other
+            off: u64::MAX,
+            addr: buffer.as_mut_ptr() as u64,
+            len: buffer.len() as u32,
+            rw_flags: 0,
+            user_data: index as u64,
+            buf_index: 0,
+            personality: 0,
+            splice_fd_in: 0,
+            addr3: 0,
+            _pad: 0,
+        };
+        unsafe {
+            self.entries
+                .at::<Submission>(0)
+                .add(slot as usize)
+                .write(entry);
+            *self.submissions.at::<u32>(offsets.array).add(slot as usize) = slot;
+        }
+        self.submissions
+            .atomic(offsets.tail)
+            .store(tail.wrapping_add(1), Ordering::Release);
+        self.reading[index] = true;
+        self.queued += 1;
+    }
+
+    /// Submit the queued reads and collect those which completed, without waiting
+    fn enter(&mut self) -> Result<()> {
+        loop {
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd(),
+                    self.queued,
+                    0,
+                    ENTER_GETEVENTS,
+                    ptr::null::<libc::sigset_t>(),
+                    0,
+                )
+            };
+            match check(result) {
+                Ok(submitted) => {
+                    self.queued -= submitted as u32;
+                    return Ok(());
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Start reading both streams
+    pub fn start(&mut self) -> Result<()> {
+        for index in 0..2 {
+            self.queue(index);
+        }
+        self.enter()
+    }
+
+    fn pop(&mut self) -> Option<Completion> {
+        let offsets = &self.params.cq_off;
+        let head = self
+            .completions
+            .atomic(offsets.head)
+            .load(Ordering::Relaxed);
+        let tail = self
+            .completions
+            .atomic(offsets.tail)
+            .load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let mask = unsafe { *self.completions.at::<u32>(offsets.ring_mask) };
+        let completion = unsafe {
+            self.completions
+                .at::<Completion>(offsets.cqes)
+                .add((head & mask) as usize)
+                .read()
+        };
+        self.completions
+            .atomic(offsets.head)
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(completion)
+    }
+
+    /// Read again what was handed out by `next`, and collect the reads which completed, which
+    /// wakes up a poll of the ring once they are there
+    pub fn submit(&mut self) -> Result<()> {
+        if let Some(index) = self.handed.take() {
+            self.queue(index);
+        }
+        self.enter()
+    }
+
+    /// The next output read from a stream, once the one handed out before was copied, or
+    /// nothing when no more was read since the last `submit`
+    ///
+    /// An empty read means the stream ended.
+    pub fn next(&mut self) -> Result<Option<(Stream, &[u8])>> {
+        if let Some(index) = self.handed.take() {
+            self.queue(index);
+        }
+        let Some(completion) = self.pop() else {
+            return Ok(None);
+        };
+        let index = completion.user_data as usize;
+        self.reading[index] = false;
+        let read = match completion.res {
+            // a pseudo-terminal reports the command closing it as an error rather than the end
+            res if res == -libc::EIO => 0,
+            res if res < 0 => return Err(io::Error::from_raw_os_error(-res)),
+            res => res as usize,
+        };
+        if read == 0 {
+            self.ended[index] = true;
+        } else {
+            self.handed = Some(index);
+        }
+        Ok(Some((STREAMS[index], &self.buffers[index][..read])))
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // the kernel may still write into buffers read into when the ring is closed, which are
+        // left to it
+        for (buffer, &reading) in self.buffers.iter_mut().zip(&self.reading) {
+            if reading {
+                mem::forget(mem::take(buffer));
+            }
+        }
+    }
+}
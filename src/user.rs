@@ -0,0 +1,105 @@
+//! Users and groups to run the command as, by name or by ID
+
+use std::{
+    ffi::{CStr, CString},
+    mem,
+    path::PathBuf,
+    ptr,
+    str::FromStr,
+};
+
+/// Size of the buffer for the strings of a passwd or group entry
+const BUFFER_SIZE: usize = 16 * 1024;
+
+pub struct User {
+    pub uid: u32,
+    /// The primary group of the user, when there is an entry for it
+    pub gid: Option<u32>,
+    pub name: Option<String>,
+    pub home: Option<PathBuf>,
+}
+
+fn string(s: *const libc::c_char) -> String {
+    unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+}
+
+/// The passwd entry of a user by name, or by `uid` when there is no name
+fn passwd(name: Option<&CStr>, uid: u32) -> Option<User> {
+    let mut entry = unsafe { mem::zeroed::<libc::passwd>() };
+    let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+    let mut found = ptr::null_mut();
+    let result = unsafe {
+        match name {
+            Some(name) => libc::getpwnam_r(
+                name.as_ptr(),
+                &mut entry,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut found,
+            ),
+            None => libc::getpwuid_r(
+                uid,
+                &mut entry,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut found,
+            ),
+        }
+    };
+    if result != 0 || found.is_null() {
+        return None;
+    }
+    Some(User {
+        uid: entry.pw_uid,
+        gid: Some(entry.pw_gid),
+        name: Some(string(entry.pw_name)),
+        home: Some(PathBuf::from(string(entry.pw_dir))),
+    })
+}
+
+/// Accepts a user name or a numeric ID, which doesn't need an entry
+impl FromStr for User {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(uid) = s.parse() {
+            return Ok(passwd(None, uid).unwrap_or(User {
+                uid,
+                gid: None,
+                name: None,
+                home: None,
+            }));
+        }
+        let name = CString::new(s).map_err(|_| format!("invalid user '{}'", s))?;
+        passwd(Some(&name), 0).ok_or_else(|| format!("unknown user '{}'", s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Group(pub u32);
+
+/// Accepts a group name or a numeric ID
+impl FromStr for Group {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(gid) = s.parse() {
+            return Ok(Group(gid));
+        }
+        let name = CString::new(s).map_err(|_| format!("invalid group '{}'", s))?;
+        let mut entry = unsafe { mem::zeroed::<libc::group>() };
+        let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+        let mut found = ptr::null_mut();
+        let result = unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                &mut entry,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut found,
+            )
+        };
+        if result != 0 || found.is_null() {
+            return Err(format!("unknown group '{}'", s));
+        }
+        Ok(Group(entry.gr_gid))
+    }
+}
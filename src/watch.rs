@@ -0,0 +1,439 @@
+//! Watching files and directories for changes
+
+mod gitignore;
+#[cfg(target_os = "linux")]
+mod inotify;
+mod poll;
+
+/// Stand-in for platforms without inotify
+#[cfg(not(target_os = "linux"))]
+mod inotify {
+    use super::{Change, Filter};
+    use std::{
+        io::{Error, ErrorKind, Result},
+        os::unix::io::{AsRawFd, RawFd},
+        path::Path,
+    };
+
+    pub struct Inotify;
+
+    impl Inotify {
+        pub fn new() -> Result<Self> {
+            Err(Error::new(
+                ErrorKind::Unsupported,
+                "inotify is only available on Linux",
+            ))
+        }
+
+        pub fn add_dir(
+            &mut self,
+            _dir: &Path,
+            _recursive: bool,
+            _filter: &mut Filter,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn read(&mut self, _changes: &mut Vec<Change>, _filter: &mut Filter) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl AsRawFd for Inotify {
+        fn as_raw_fd(&self) -> RawFd {
+            -1
+        }
+    }
+}
+
+use crate::glob::{self, Pattern};
+use gitignore::Rules;
+use mio::{unix::SourceFd, Interest, Registry, Token};
+use poll::Poller;
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::{Error, ErrorKind, Result},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+/// Interval of the polling watcher where inotify is not available
+const FALLBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Create,
+    Modify,
+    Remove,
+    Rename,
+    Metadata,
+}
+
+impl Kind {
+    pub const ALL: &'static [Kind] = &[
+        Kind::Create,
+        Kind::Modify,
+        Kind::Remove,
+        Kind::Rename,
+        Kind::Metadata,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Create => "create",
+            Kind::Modify => "modify",
+            Kind::Remove => "remove",
+            Kind::Rename => "rename",
+            Kind::Metadata => "metadata",
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Kind::Create => "created",
+            Kind::Modify => "modified",
+            Kind::Remove => "removed",
+            Kind::Rename => "renamed",
+            Kind::Metadata => "touched",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Kind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown event kind '{}'", s))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: Kind,
+}
+
+impl Change {
+    /// The path without a leading `./`
+    pub fn display_path(&self) -> &Path {
+        self.path.strip_prefix(".").unwrap_or(&self.path)
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.display_path().display(), self.kind.verb())
+    }
+}
+
+enum Root {
+    /// Everything below a directory
+    Dir(PathBuf),
+    /// A single file, watched through its parent directory so replacing it is noticed
+    File(PathBuf),
+    /// Paths matching a glob, watched through the directory the glob starts in
+    Glob(Pattern),
+}
+
+/// Paths excluded from watching by `--ignore` patterns and ignore files
+pub struct Filter {
+    ignore: Vec<Pattern>,
+    /// Rules of the ignore files read so far by absolute directory, `None` when they are not used
+    ignore_files: Option<BTreeMap<PathBuf, Rules>>,
+    /// Absolute paths of the recursively watched directories
+    roots: Vec<PathBuf>,
+    cwd: PathBuf,
+}
+
+impl Filter {
+    fn new(ignore: &[Pattern], ignore_files: bool) -> Result<Self> {
+        Ok(Self {
+            ignore: ignore.to_vec(),
+            ignore_files: ignore_files.then(BTreeMap::new),
+            roots: Vec::new(),
+            cwd: env::current_dir()?,
+        })
+    }
+
+    /// Read the ignore files in a directory, replacing what was read from it before
+    pub fn load(&mut self, dir: &Path) {
+        if let Some(ignore_files) = &mut self.ignore_files {
+            let dir = self.cwd.join(dir);
+            match Rules::load(&dir) {
+                Some(rules) => ignore_files.insert(dir, rules),
+                None => ignore_files.remove(&dir),
+            };
+        }
+    }
+
+    /// Note a recursively watched directory and read the ignore files of the directories above
+    /// it up to the root of its repository
+    fn add_root(&mut self, dir: &Path) {
+        let dir = self.cwd.join(dir);
+        self.roots.push(dir.clone());
+        let repo = match dir.ancestors().find(|dir| dir.join(".git").exists()) {
+            Some(repo) => repo,
+            None => return,
+        };
+        for parent in dir.ancestors().skip(1) {
+            if !parent.starts_with(repo) {
+                break;
+            }
+            self.load(parent);
+        }
+    }
+
+    /// Whether a path is excluded by either `--ignore` patterns or ignore files
+    pub fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        self.ignores(path) || self.ignored_by_files(path, is_dir)
+    }
+
+    /// Patterns without a `/` match any component of a path, others the path or a parent of it
+    fn ignores(&self, path: &Path) -> bool {
+        self.ignore.iter().any(|pattern| {
+            if pattern.has_separator() {
+                path.ancestors().any(|ancestor| pattern.matches(ancestor))
+            } else {
+                path.components()
+                    .any(|component| pattern.matches(Path::new(component.as_os_str())))
+            }
+        })
+    }
+
+    /// Like git, a path is ignored when it or any directory above it is, and deeper ignore
+    /// files take precedence over the ones above them
+    ///
+    /// Only directories below the watched directory are considered, so that watching an
+    /// ignored directory by name works.
+    fn ignored_by_files(&self, path: &Path, is_dir: bool) -> bool {
+        let ignore_files = match &self.ignore_files {
+            Some(ignore_files) => ignore_files,
+            None => return false,
+        };
+        let path = self.cwd.join(path);
+        let root = self
+            .roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        let mut ancestors = path
+            .ancestors()
+            .filter(|ancestor| root.is_none_or(|root| !root.starts_with(ancestor)))
+            .collect::<Vec<_>>();
+        if ancestors
+            .iter()
+            .any(|ancestor| ancestor.file_name() == Some(".git".as_ref()))
+        {
+            return true;
+        }
+        ancestors.reverse();
+        ancestors.iter().any(|ancestor| {
+            let is_dir = is_dir || *ancestor != path;
+            ignore_files
+                .iter()
+                .rev()
+                .filter(|(dir, _)| ancestor.starts_with(dir) && ancestor != dir)
+                .find_map(|(dir, rules)| {
+                    let relative = ancestor.strip_prefix(dir).ok()?;
+                    rules.matched(relative, is_dir)
+                })
+                == Some(true)
+        })
+    }
+}
+
+/// What git changes when switching branches, committing or pulling, the `HEAD` file and the
+/// local branches
+pub fn git_paths() -> Result<Vec<PathBuf>> {
+    let cwd = env::current_dir()?;
+    let repo = cwd
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "not inside a git repository"))?;
+    let mut git_dir = repo.join(".git");
+    if git_dir.is_file() {
+        // worktrees and submodules point to their actual git directory
+        let link = fs::read_to_string(&git_dir)?;
+        let target = link.trim().strip_prefix("gitdir:").ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: expected a gitdir line", git_dir.display()),
+            )
+        })?;
+        git_dir = repo.join(target.trim());
+    }
+    // the branches of a worktree are in the directory it was created from
+    let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.clone(),
+    };
+    let relative = |path: PathBuf| match path.strip_prefix(&cwd) {
+        Ok(relative) => relative.to_owned(),
+        Err(_) => path,
+    };
+    Ok(vec![
+        relative(git_dir.join("HEAD")),
+        relative(common_dir.join("refs").join("heads")),
+    ])
+}
+
+/// How changes are noticed
+enum Backend {
+    Inotify(inotify::Inotify),
+    Poll(Poller),
+}
+
+impl Backend {
+    fn new(poll: Option<Duration>) -> Result<Self> {
+        if let Some(interval) = poll {
+            return Ok(Backend::Poll(Poller::new(interval)));
+        }
+        match inotify::Inotify::new() {
+            Ok(inotify) => Ok(Backend::Inotify(inotify)),
+            Err(err) if err.kind() == ErrorKind::Unsupported => {
+                Ok(Backend::Poll(Poller::new(FALLBACK_INTERVAL)))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn add_dir(&mut self, dir: &Path, recursive: bool, filter: &mut Filter) -> Result<()> {
+        match self {
+            Backend::Inotify(inotify) => inotify.add_dir(dir, recursive, filter),
+            Backend::Poll(poller) => poller.add_dir(dir, recursive, filter),
+        }
+    }
+
+    fn read(&mut self, changes: &mut Vec<Change>, filter: &mut Filter) -> Result<()> {
+        match self {
+            Backend::Inotify(inotify) => inotify.read(changes, filter),
+            Backend::Poll(poller) => poller.read(changes, filter),
+        }
+    }
+}
+
+pub struct Watcher {
+    backend: Backend,
+    roots: Vec<Root>,
+    filter: Filter,
+    /// Kinds of changes that are reported
+    kinds: Vec<Kind>,
+}
+
+impl Watcher {
+    /// Watch through inotify, or by polling at an interval when `poll` is given
+    pub fn new(
+        paths: &[PathBuf],
+        ignore: &[Pattern],
+        ignore_files: bool,
+        poll: Option<Duration>,
+        kinds: &[Kind],
+    ) -> Result<Self> {
+        let mut backend = Backend::new(poll)?;
+        let mut roots = Vec::new();
+        let mut filter = Filter::new(ignore, ignore_files)?;
+        for path in paths {
+            let text = path.to_string_lossy();
+            if glob::is_glob(&text) {
+                let pattern =
+                    Pattern::new(&text).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+                let base = glob::base(&text);
+                base.metadata().map_err(|err| {
+                    Error::new(err.kind(), format!("{}: {}", base.display(), err))
+                })?;
+                filter.add_root(&base);
+                backend.add_dir(&base, true, &mut filter)?;
+                roots.push(Root::Glob(pattern));
+                continue;
+            }
+            let meta = path
+                .metadata()
+                .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+            if meta.is_dir() {
+                filter.add_root(path);
+                backend.add_dir(path, true, &mut filter)?;
+                roots.push(Root::Dir(path.clone()));
+            } else {
+                let parent = match path.parent() {
+                    Some(parent) if parent != Path::new("") => parent,
+                    _ => Path::new("."),
+                };
+                backend.add_dir(parent, false, &mut filter)?;
+                roots.push(Root::File(
+                    parent.join(path.file_name().unwrap_or_default()),
+                ));
+            }
+        }
+        Ok(Self {
+            backend,
+            roots,
+            filter,
+            kinds: kinds.to_vec(),
+        })
+    }
+
+    /// Ignore files only apply below watched directories, a file that was named explicitly
+    /// is always watched
+    fn accepts(&self, change: &Change) -> bool {
+        let ignored = || {
+            self.filter
+                .ignored_by_files(&change.path, change.path.is_dir())
+        };
+        self.kinds.contains(&change.kind)
+            && !self.filter.ignores(&change.path)
+            && self.roots.iter().any(|root| match root {
+                Root::Dir(dir) => change.path.starts_with(dir) && !ignored(),
+                Root::File(file) => change.path == *file,
+                Root::Glob(pattern) => pattern.matches(&change.path) && !ignored(),
+            })
+    }
+
+    /// Changes to watched paths since the last call, each path reported once
+    ///
+    /// Polling only looks for changes once its interval has passed, so this is meant to be
+    /// called regularly and not just when the watcher is readable
+    pub fn read(&mut self) -> Result<Vec<Change>> {
+        let mut changes = Vec::new();
+        self.backend.read(&mut changes, &mut self.filter)?;
+        for change in &changes {
+            if Rules::is_ignore_file(&change.path) {
+                self.filter
+                    .load(change.path.parent().unwrap_or(Path::new(".")));
+            }
+        }
+        let mut seen = Vec::<PathBuf>::new();
+        changes.retain(|change| {
+            if !self.accepts(change) || seen.contains(&change.path) {
+                return false;
+            }
+            seen.push(change.path.clone());
+            true
+        });
+        Ok(changes)
+    }
+
+    /// Wake up `registry` when there are changes, if the backend can tell
+    pub fn register(&self, registry: &Registry, token: Token) -> Result<()> {
+        match &self.backend {
+            Backend::Inotify(inotify) => registry.register(
+                &mut SourceFd(&inotify.as_raw_fd()),
+                token,
+                Interest::READABLE,
+            ),
+            Backend::Poll(_) => Ok(()),
+        }
+    }
+
+    pub fn deregister(&self, registry: &Registry) -> Result<()> {
+        match &self.backend {
+            Backend::Inotify(inotify) => registry.deregister(&mut SourceFd(&inotify.as_raw_fd())),
+            Backend::Poll(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Rules from `.gitignore` and `.ignore` files
+
+use crate::glob::Pattern;
+use std::{fs, path::Path};
+
+/// Files read in every watched directory, later files taking precedence
+const FILE_NAMES: &[&str] = &[".gitignore", ".ignore"];
+
+struct Rule {
+    pattern: Pattern,
+    /// A leading `!` includes paths an earlier rule ignored
+    negated: bool,
+    /// A trailing `/` only matches directories
+    dir_only: bool,
+    /// Patterns with a `/` before the end match relative to the directory of the file,
+    /// others match the name of a path at any depth
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+        Some(Self {
+            pattern,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
+
+/// The rules of the ignore files in one directory
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    /// Read the ignore files in `dir`, if there are any
+    pub fn load(dir: &Path) -> Option<Self> {
+        let rules = FILE_NAMES
+            .iter()
+            .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+            .flat_map(|text| text.lines().filter_map(Rule::parse).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            None
+        } else {
+            Some(Self(rules))
+        }
+    }
+
+    pub fn is_ignore_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| FILE_NAMES.iter().any(|file| name == *file))
+    }
+
+    /// Whether the last rule matching `path`, relative to the directory of the rules,
+    /// ignores or includes it
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let name = Path::new(path.file_name()?);
+        self.0
+            .iter()
+            .rev()
+            .filter(|rule| is_dir || !rule.dir_only)
+            .find(|rule| {
+                if rule.anchored {
+                    rule.pattern.matches(path)
+                } else {
+                    rule.pattern.matches(name)
+                }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
@@ -0,0 +1,161 @@
+use super::{Change, Filter, Kind};
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    fs,
+    io::{Error, ErrorKind, Result},
+    mem,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
+};
+
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_ATTRIB
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF
+    | libc::IN_ONLYDIR;
+
+/// Watches directories through the Linux inotify API
+pub struct Inotify {
+    fd: RawFd,
+    /// Watched directory and whether its subdirectories are watched too, by watch descriptor
+    dirs: HashMap<i32, (PathBuf, bool)>,
+    buf: Vec<u8>,
+}
+
+impl Inotify {
+    pub fn new() -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self {
+            fd,
+            dirs: HashMap::new(),
+            buf: vec![0; 64 * 1024],
+        })
+    }
+
+    pub fn add_dir(&mut self, dir: &Path, recursive: bool, filter: &mut Filter) -> Result<()> {
+        let path = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), MASK) };
+        if wd < 0 {
+            let err = Error::last_os_error();
+            return Err(match err.raw_os_error() {
+                Some(libc::ENOSPC) => Error::new(
+                    err.kind(),
+                    "inotify watch limit reached, raise fs.inotify.max_user_watches",
+                ),
+                _ => Error::new(err.kind(), format!("{}: {}", dir.display(), err)),
+            });
+        }
+        // the same directory may be reached through several watched paths
+        self.dirs
+            .entry(wd)
+            .and_modify(|(_, watched)| *watched |= recursive)
+            .or_insert_with(|| (dir.to_owned(), recursive));
+        if recursive {
+            filter.load(dir);
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                // ignored directories are not watched at all, which keeps large trees like
+                // `target` from using up inotify watches
+                if entry.file_type()?.is_dir() && !filter.excludes(&entry.path(), true) {
+                    self.add_dir(&entry.path(), true, filter)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all pending events without blocking
+    pub fn read(&mut self, changes: &mut Vec<Change>, filter: &mut Filter) -> Result<()> {
+        loop {
+            let read = unsafe { libc::read(self.fd, self.buf.as_mut_ptr().cast(), self.buf.len()) };
+            if read < 0 {
+                let err = Error::last_os_error();
+                return match err.kind() {
+                    ErrorKind::WouldBlock => Ok(()),
+                    ErrorKind::Interrupted => continue,
+                    _ => Err(err),
+                };
+            }
+            let mut offset = 0;
+            let read = read as usize;
+            let mut new_dirs = Vec::new();
+            while offset + mem::size_of::<libc::inotify_event>() <= read {
+                let event = unsafe {
+                    (self.buf.as_ptr().add(offset) as *const libc::inotify_event).read_unaligned()
+                };
+                let name_start = offset + mem::size_of::<libc::inotify_event>();
+                let name = &self.buf[name_start..name_start + event.len as usize];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                offset = name_start + event.len as usize;
+
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    // events were lost, report every watched directory as modified
+                    changes.extend(self.dirs.values().map(|(dir, _)| Change {
+                        path: dir.clone(),
+                        kind: Kind::Modify,
+                    }));
+                    continue;
+                }
+                let (dir, recursive) = match self.dirs.get(&event.wd) {
+                    Some((dir, recursive)) => (dir.clone(), *recursive),
+                    None => continue,
+                };
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let path = if name.is_empty() {
+                    dir
+                } else {
+                    dir.join(OsStr::from_bytes(name))
+                };
+                let kind = if event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                    if recursive
+                        && event.mask & libc::IN_ISDIR != 0
+                        && !filter.excludes(&path, true)
+                    {
+                        new_dirs.push(path.clone());
+                    }
+                    Kind::Create
+                } else if event.mask & (libc::IN_DELETE | libc::IN_DELETE_SELF) != 0 {
+                    Kind::Remove
+                } else if event.mask & (libc::IN_MOVED_FROM | libc::IN_MOVE_SELF) != 0 {
+                    Kind::Rename
+                } else if event.mask & libc::IN_ATTRIB != 0 {
+                    Kind::Metadata
+                } else {
+                    Kind::Modify
+                };
+                changes.push(Change { path, kind });
+            }
+            for dir in new_dirs {
+                // the directory may already be gone again, which is not an error
+                let _ = self.add_dir(&dir, true, filter);
+            }
+        }
+    }
+}
+
+impl AsRawFd for Inotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
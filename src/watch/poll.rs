@@ -0,0 +1,134 @@
+use super::{Change, Filter, Kind};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Result,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// What is compared between scans to notice a change
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    is_dir: bool,
+    ino: u64,
+    len: u64,
+    mtime: (i64, i64),
+    ctime: (i64, i64),
+    mode: u32,
+}
+
+impl Stamp {
+    fn new(meta: &fs::Metadata) -> Self {
+        Self {
+            is_dir: meta.is_dir(),
+            ino: meta.ino(),
+            len: meta.len(),
+            mtime: (meta.mtime(), meta.mtime_nsec()),
+            ctime: (meta.ctime(), meta.ctime_nsec()),
+            mode: meta.mode(),
+        }
+    }
+
+    fn compare(&self, old: &Stamp) -> Option<Kind> {
+        if self.is_dir && old.is_dir {
+            // new and removed entries are reported on their own
+            None
+        } else if self.ino != old.ino || self.len != old.len || self.mtime != old.mtime {
+            Some(Kind::Modify)
+        } else if self.mode != old.mode || self.ctime != old.ctime {
+            Some(Kind::Metadata)
+        } else {
+            None
+        }
+    }
+}
+
+/// Watches directories by comparing the metadata of their entries at an interval, for file
+/// systems where inotify sees no events like NFS or some container bind mounts
+pub struct Poller {
+    interval: Duration,
+    next: Instant,
+    /// Watched directory and whether its subdirectories are watched too
+    dirs: Vec<(PathBuf, bool)>,
+    stamps: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Poller {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Instant::now() + interval,
+            dirs: Vec::new(),
+            stamps: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_dir(&mut self, dir: &Path, recursive: bool, filter: &mut Filter) -> Result<()> {
+        fs::metadata(dir)?;
+        self.dirs.push((dir.to_owned(), recursive));
+        let mut stamps = BTreeMap::new();
+        scan(dir, recursive, filter, &mut stamps);
+        self.stamps.extend(stamps);
+        Ok(())
+    }
+
+    /// Compare against the previous scan once the interval has passed
+    pub fn read(&mut self, changes: &mut Vec<Change>, filter: &mut Filter) -> Result<()> {
+        let now = Instant::now();
+        if now < self.next {
+            return Ok(());
+        }
+        self.next = now + self.interval;
+
+        let mut stamps = BTreeMap::new();
+        for (dir, recursive) in &self.dirs {
+            scan(dir, *recursive, filter, &mut stamps);
+        }
+        for (path, stamp) in &stamps {
+            let kind = match self.stamps.get(path) {
+                Some(old) => stamp.compare(old),
+                None => Some(Kind::Create),
+            };
+            if let Some(kind) = kind {
+                changes.push(Change {
+                    path: path.clone(),
+                    kind,
+                });
+            }
+        }
+        for path in self.stamps.keys() {
+            if !stamps.contains_key(path) {
+                changes.push(Change {
+                    path: path.clone(),
+                    kind: Kind::Remove,
+                });
+            }
+        }
+        self.stamps = stamps;
+        Ok(())
+    }
+}
+
+/// Record the entries of a directory, which may have disappeared since it was watched
+fn scan(dir: &Path, recursive: bool, filter: &mut Filter, stamps: &mut BTreeMap<PathBuf, Stamp>) {
+    if recursive {
+        filter.load(dir);
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        if recursive && meta.is_dir() && !filter.excludes(&path, true) {
+            scan(&path, true, filter, stamps);
+        }
+        stamps.insert(path, Stamp::new(&meta));
+    }
+}